@@ -1,10 +1,27 @@
+#![cfg_attr(feature = "bench", feature(test))]
+#![allow(clippy::needless_return)]
+
+#[cfg(feature = "bench")]
+extern crate test;
+
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::collections;
 use std::collections::hash_map::Entry;
 
-const MEMORY_BANK_COUNT: usize = 16;
-type MemoryBanks = [usize; MEMORY_BANK_COUNT];
+#[cfg(feature = "bench")]
+use test::Bencher;
+
+type MemoryBanks = Vec<u64>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CycleDetection {
+    // Remembers every configuration seen, so memory grows with the number of steps
+    HashMap,
+    // Brent's algorithm, only ever keeps two configurations around
+    Brent,
+}
 
 fn get_input() -> MemoryBanks {
     let mut f = File::open("input.txt")
@@ -14,52 +31,539 @@ fn get_input() -> MemoryBanks {
     f.read_to_string(&mut input_str)
         .expect("Could not read file");
 
-    let input_iter = input_str.split_whitespace().map(|x| {
-        x.parse::<usize>().expect("Non number found in input")
-    });
+    let result: MemoryBanks = input_str.split_whitespace().map(|x| {
+        x.parse::<u64>().expect("Non number found in input")
+    }).collect();
+
+    if result.is_empty() {
+        panic!("No memory banks in input");
+    }
+
+    return result;
+}
+
+// Decides which bank gets emptied and where its blocks go for each step
+trait Redistribution {
+    fn reallocate(&self, memory: &mut MemoryBanks);
+}
+
+// Hands out value blocks one at a time to the banks following source_index,
+// wrapping around and possibly ending back at source_index itself
+fn round_robin(memory: &mut MemoryBanks, source_index: usize, value: u64) {
+    // Every bank gets the same share, then the remainder is one more block for each
+    // bank in the (possibly wrapping) interval right after source_index
+    let bank_count = memory.len();
+    let per_bank_value = value / bank_count as u64;
+    let remainder = (value % bank_count as u64) as usize;
+    for bank in memory.iter_mut() {
+        *bank += per_bank_value;
+    }
+    let (start, end) = remainder_interval(bank_count, source_index, remainder);
+    if start <= end {
+        increment_range(&mut memory[start..end]);
+    } else {
+        increment_range(&mut memory[start..]);
+        increment_range(&mut memory[..end]);
+    }
+}
+
+// Returns the [start, end) interval of the remainder banks, with start > end when it wraps
+fn remainder_interval(bank_count: usize, source_index: usize, remainder: usize) -> (usize, usize) {
+    let start = (source_index + 1) % bank_count;
+    let end = (source_index + remainder) % bank_count + 1;
+    if remainder == 0 {
+        return (start, start);
+    }
+    return (start, end);
+}
+
+fn increment_range(banks: &mut [u64]) {
+    for bank in banks.iter_mut() {
+        *bank += 1;
+    }
+}
+
+// The puzzle's allocator: empty the fullest bank, lowest index wins ties
+struct RoundRobinFromMax;
+
+impl Redistribution for RoundRobinFromMax {
+    fn reallocate(&self, memory: &mut MemoryBanks) {
+        let (max_index, &value) = memory.iter().enumerate().max_by_key(|&(i, v)| (v, -(i as isize))).unwrap();
+        memory[max_index] = 0;
+        round_robin(memory, max_index, value);
+    }
+}
+
+// Empty the least full non-empty bank, lowest index wins ties
+struct RoundRobinFromMin;
+
+impl Redistribution for RoundRobinFromMin {
+    fn reallocate(&self, memory: &mut MemoryBanks) {
+        let min = memory.iter().enumerate().filter(|&(_, &v)| v > 0).min_by_key(|&(i, v)| (v, i));
+        if let Some((min_index, &value)) = min {
+            memory[min_index] = 0;
+            round_robin(memory, min_index, value);
+        }
+    }
+}
+
+// Empty the fullest bank, breaking ties pseudo-randomly. The choice is derived from
+// the seed and the current configuration so the simulation still only depends on
+// the configuration, which the cycle detection relies on.
+struct RandomTieBreak {
+    seed: u64,
+}
+
+impl RandomTieBreak {
+    fn configuration_hash(&self, memory: &MemoryBanks) -> u64 {
+        // FNV-1a over the seed and bank values
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for value in ::std::iter::once(self.seed).chain(memory.iter().cloned()) {
+            for byte in 0..8 {
+                hash ^= (value >> (byte * 8)) & 0xff;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        return hash;
+    }
+}
 
-    let mut result: MemoryBanks = Default::default();
-    let mut count = 0;
-    for (output, input) in (&mut result).into_iter().zip(input_iter) {
-        *output = input;
-        count += 1;
+impl Redistribution for RandomTieBreak {
+    fn reallocate(&self, memory: &mut MemoryBanks) {
+        let value = *memory.iter().max().unwrap();
+        let candidates: Vec<usize> = (0..memory.len()).filter(|&i| memory[i] == value).collect();
+        let max_index = candidates[(self.configuration_hash(memory) % candidates.len() as u64) as usize];
+        memory[max_index] = 0;
+        round_robin(memory, max_index, value);
+    }
+}
+
+// Empty the fullest bank and hand its blocks out in rounds, the bank at offset i + 1
+// receiving weights[i] blocks per round. Weights of all 1 for every bank is the same
+// as RoundRobinFromMax.
+struct WeightedNeighbors {
+    weights: Vec<u64>,
+}
+
+impl WeightedNeighbors {
+    fn new(weights: Vec<u64>) -> WeightedNeighbors {
+        if weights.iter().sum::<u64>() == 0 {
+            panic!("Neighbor weights must not all be zero");
+        }
+        return WeightedNeighbors { weights };
     }
+}
+
+impl Redistribution for WeightedNeighbors {
+    fn reallocate(&self, memory: &mut MemoryBanks) {
+        let (max_index, &value) = memory.iter().enumerate().max_by_key(|&(i, v)| (v, -(i as isize))).unwrap();
+        memory[max_index] = 0;
 
-    if count != MEMORY_BANK_COUNT {
-        panic!("Incorrect number of values in input");
+        let bank_count = memory.len();
+        let total_weight: u64 = self.weights.iter().sum();
+        let full_rounds = value / total_weight;
+        let mut remainder = value % total_weight;
+        for (offset, &weight) in self.weights.iter().enumerate() {
+            let index = (max_index + offset + 1) % bank_count;
+            let partial = ::std::cmp::min(weight, remainder);
+            memory[index] += full_rounds * weight + partial;
+            remainder -= partial;
+        }
     }
+}
 
+fn parse_strategy(name: &str) -> Box<dyn Redistribution> {
+    let mut parts = name.splitn(2, ':');
+    let strategy: Box<dyn Redistribution> = match (parts.next().unwrap(), parts.next()) {
+        ("max", None) => Box::new(RoundRobinFromMax),
+        ("min", None) => Box::new(RoundRobinFromMin),
+        ("random", Some(seed)) => Box::new(RandomTieBreak {
+            seed: seed.parse().expect("Random seed must be a number"),
+        }),
+        ("weighted", Some(weights)) => Box::new(WeightedNeighbors::new(
+            weights.split(',').map(|w| w.parse().expect("Neighbor weight must be a number")).collect(),
+        )),
+        _ => panic!("Unknown redistribution strategy {}, expected max, min, random:SEED or weighted:W1,W2,...", name),
+    };
+    return strategy;
+}
+
+fn reallocate<R: Redistribution + ?Sized>(memory: &MemoryBanks, strategy: &R) -> MemoryBanks {
+    let mut result = memory.clone();
+    strategy.reallocate(&mut result);
     return result;
 }
 
-fn part12() -> (usize, usize) {
-    let mut current_memory = get_input();
+fn part12_hash_map<R: Redistribution + ?Sized>(initial_memory: &MemoryBanks, strategy: &R) -> (usize, usize) {
+    let mut current_memory = initial_memory.clone();
     let mut seen_configurations: collections::HashMap<MemoryBanks, usize> = collections::HashMap::new();
 
     let mut step: usize = 0;
     loop {
+        let next_memory = reallocate(&current_memory, strategy);
         match seen_configurations.entry(current_memory) {
             Entry::Occupied(entry) => return (step, step - entry.get()),
             Entry::Vacant(entry) => entry.insert(step),
         };
 
-        let (max_index, &value) = current_memory.iter().enumerate().max_by_key(|&(i, v)| (v, -(i as i32))).unwrap();
-        current_memory[max_index] = 0;
-        let per_bank_value = value / MEMORY_BANK_COUNT;
-        let remainder = value % MEMORY_BANK_COUNT;
-        for offset in 1..MEMORY_BANK_COUNT + 1 {
-            let index = (max_index + offset) % current_memory.len();
-            current_memory[index] += per_bank_value;
-            if offset <= remainder {
-                current_memory[index] += 1;
+        current_memory = next_memory;
+        step += 1;
+    }
+}
+
+fn part12_brent<R: Redistribution + ?Sized>(initial_memory: &MemoryBanks, strategy: &R) -> (usize, usize) {
+    // Find the loop length by moving the hare ahead in powers of two and
+    // teleporting the tortoise to it each time the power is used up
+    let mut power: usize = 1;
+    let mut loop_length: usize = 1;
+    let mut tortoise = initial_memory.clone();
+    let mut hare = reallocate(initial_memory, strategy);
+    while tortoise != hare {
+        if power == loop_length {
+            tortoise = hare.clone();
+            power *= 2;
+            loop_length = 0;
+        }
+        hare = reallocate(&hare, strategy);
+        loop_length += 1;
+    }
+
+    // With the hare a full loop ahead of the tortoise, they meet at the start of the loop
+    let mut tortoise = initial_memory.clone();
+    let mut hare = initial_memory.clone();
+    for _ in 0..loop_length {
+        hare = reallocate(&hare, strategy);
+    }
+    let mut loop_start: usize = 0;
+    while tortoise != hare {
+        tortoise = reallocate(&tortoise, strategy);
+        hare = reallocate(&hare, strategy);
+        loop_start += 1;
+    }
+
+    return (loop_start + loop_length, loop_length);
+}
+
+// Banks for RoundRobinFromMax stored in a segment tree with lazy range adds, so a
+// step only costs O(log n) no matter how many banks there are or how big the values get.
+// A hash that is linear in the bank values is kept up to date along the way so
+// configurations can be compared without looking at every bank.
+#[derive(Clone)]
+struct BankTree {
+    bank_count: usize,
+    // Per node max value of the node's range (not counting lazy adds of its ancestors),
+    // the lowest index holding that max, and the add still to be pushed to its children
+    max: Vec<u64>,
+    max_index: Vec<usize>,
+    lazy: Vec<u64>,
+    // hash = sum(bank_weight(i) * value(i)) using wrapping arithmetic
+    hash: u64,
+    // weight_prefix_sums[i] = sum(bank_weight(j)) for j < i
+    weight_prefix_sums: Vec<u64>,
+}
+
+fn bank_weight(index: usize) -> u64 {
+    // splitmix64 finalizer, so the weights look random but are the same for every tree
+    let mut z = (index as u64).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+impl BankTree {
+    fn new(memory: &MemoryBanks) -> BankTree {
+        let bank_count = memory.len();
+        let mut weight_prefix_sums: Vec<u64> = vec![0; bank_count + 1];
+        let mut hash: u64 = 0;
+        for (i, &value) in memory.iter().enumerate() {
+            weight_prefix_sums[i + 1] = weight_prefix_sums[i].wrapping_add(bank_weight(i));
+            hash = hash.wrapping_add(bank_weight(i).wrapping_mul(value));
+        }
+
+        let mut result = BankTree {
+            bank_count,
+            max: vec![0; 4 * bank_count],
+            max_index: vec![0; 4 * bank_count],
+            lazy: vec![0; 4 * bank_count],
+            hash,
+            weight_prefix_sums,
+        };
+        result.build(1, 0, bank_count, memory);
+        return result;
+    }
+
+    fn build(&mut self, node: usize, lo: usize, hi: usize, memory: &MemoryBanks) {
+        if hi - lo == 1 {
+            self.max[node] = memory[lo];
+            self.max_index[node] = lo;
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.build(2 * node, lo, mid, memory);
+        self.build(2 * node + 1, mid, hi, memory);
+        self.pull(node);
+    }
+
+    fn pull(&mut self, node: usize) {
+        let (left, right) = (2 * node, 2 * node + 1);
+        let child = if self.max[left] >= self.max[right] { left } else { right };
+        self.max[node] = self.max[child];
+        self.max_index[node] = self.max_index[child];
+    }
+
+    fn push(&mut self, node: usize) {
+        let add = self.lazy[node];
+        if add != 0 {
+            for child in 2 * node..2 * node + 2 {
+                self.max[child] += add;
+                self.lazy[child] += add;
             }
+            self.lazy[node] = 0;
         }
-        step += 1;
     }
+
+    fn add_range(&mut self, node: usize, lo: usize, hi: usize, start: usize, end: usize, add: u64) {
+        if end <= lo || hi <= start {
+            return;
+        }
+        if start <= lo && hi <= end {
+            self.max[node] += add;
+            self.lazy[node] += add;
+            return;
+        }
+        self.push(node);
+        let mid = (lo + hi) / 2;
+        self.add_range(2 * node, lo, mid, start, end, add);
+        self.add_range(2 * node + 1, mid, hi, start, end, add);
+        self.pull(node);
+    }
+
+    fn clear(&mut self, node: usize, lo: usize, hi: usize, index: usize) {
+        if hi - lo == 1 {
+            self.max[node] = 0;
+            return;
+        }
+        self.push(node);
+        let mid = (lo + hi) / 2;
+        if index < mid {
+            self.clear(2 * node, lo, mid, index);
+        } else {
+            self.clear(2 * node + 1, mid, hi, index);
+        }
+        self.pull(node);
+    }
+
+    fn increment(&mut self, start: usize, end: usize) {
+        let bank_count = self.bank_count;
+        self.add_range(1, 0, bank_count, start, end, 1);
+        self.hash = self.hash.wrapping_add(self.weight_prefix_sums[end].wrapping_sub(self.weight_prefix_sums[start]));
+    }
+
+    fn reallocate(&mut self) {
+        let bank_count = self.bank_count;
+        let (max_index, value) = (self.max_index[1], self.max[1]);
+        self.clear(1, 0, bank_count, max_index);
+        self.hash = self.hash.wrapping_sub(bank_weight(max_index).wrapping_mul(value));
+
+        // Same split as round_robin, the share for every bank is a lazy add on the root
+        let per_bank_value = value / bank_count as u64;
+        let remainder = (value % bank_count as u64) as usize;
+        self.max[1] += per_bank_value;
+        self.lazy[1] += per_bank_value;
+        self.hash = self.hash.wrapping_add(per_bank_value.wrapping_mul(self.weight_prefix_sums[bank_count]));
+        let (start, end) = remainder_interval(bank_count, max_index, remainder);
+        if start <= end {
+            self.increment(start, end);
+        } else {
+            self.increment(start, bank_count);
+            self.increment(0, end);
+        }
+    }
+
+    fn to_memory_banks(&self) -> MemoryBanks {
+        let mut result = vec![0; self.bank_count];
+        self.collect(1, 0, self.bank_count, 0, &mut result);
+        return result;
+    }
+
+    fn collect(&self, node: usize, lo: usize, hi: usize, add: u64, result: &mut MemoryBanks) {
+        if hi - lo == 1 {
+            result[lo] = self.max[node] + add;
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let add = add + self.lazy[node];
+        self.collect(2 * node, lo, mid, add, result);
+        self.collect(2 * node + 1, mid, hi, add, result);
+    }
+}
+
+impl PartialEq for BankTree {
+    fn eq(&self, other: &BankTree) -> bool {
+        // Only walk the banks when the hashes collide
+        return self.hash == other.hash && self.to_memory_banks() == other.to_memory_banks();
+    }
+}
+
+fn reallocate_tree(memory: &BankTree) -> BankTree {
+    let mut result = memory.clone();
+    result.reallocate();
+    return result;
+}
+
+// Same as part12_brent with RoundRobinFromMax, but with the banks in a BankTree. The tree is built
+// around that strategy, so unlike part12 it can't take another one.
+fn part12_tree(initial_memory: &MemoryBanks) -> (usize, usize) {
+    let initial_tree = BankTree::new(initial_memory);
+
+    let mut power: usize = 1;
+    let mut loop_length: usize = 1;
+    let mut tortoise = initial_tree.clone();
+    let mut hare = reallocate_tree(&initial_tree);
+    while tortoise != hare {
+        if power == loop_length {
+            tortoise = hare.clone();
+            power *= 2;
+            loop_length = 0;
+        }
+        hare.reallocate();
+        loop_length += 1;
+    }
+
+    let mut tortoise = initial_tree.clone();
+    let mut hare = initial_tree;
+    for _ in 0..loop_length {
+        hare.reallocate();
+    }
+    let mut loop_start: usize = 0;
+    while tortoise != hare {
+        tortoise.reallocate();
+        hare.reallocate();
+        loop_start += 1;
+    }
+
+    return (loop_start + loop_length, loop_length);
+}
+
+fn part12<R: Redistribution + ?Sized>(initial_memory: &MemoryBanks, strategy: &R, method: CycleDetection) -> (usize, usize) {
+    match method {
+        CycleDetection::HashMap => part12_hash_map(initial_memory, strategy),
+        CycleDetection::Brent => part12_brent(initial_memory, strategy),
+    }
+}
+
+#[test]
+fn test_example() {
+    let memory = vec![0, 2, 7, 0];
+    assert_eq!((5, 4), part12(&memory, &RoundRobinFromMax, CycleDetection::HashMap));
+    assert_eq!((5, 4), part12(&memory, &RoundRobinFromMax, CycleDetection::Brent));
+    assert_eq!((5, 4), part12(&memory, &WeightedNeighbors::new(vec![1; 4]), CycleDetection::Brent));
+}
+
+#[test]
+fn test_brent_matches_hash_map() {
+    let memory = vec![2, 0, 7, 0, 0, 31, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    let strategies: Vec<Box<dyn Redistribution>> = vec![
+        Box::new(RoundRobinFromMax),
+        Box::new(RoundRobinFromMin),
+        Box::new(RandomTieBreak { seed: 42 }),
+        Box::new(WeightedNeighbors::new(vec![3, 0, 1, 2])),
+    ];
+    for strategy in strategies.iter() {
+        assert_eq!(part12(&memory, strategy.as_ref(), CycleDetection::HashMap),
+                   part12(&memory, strategy.as_ref(), CycleDetection::Brent));
+    }
+
+    let memory = vec![0; 16];
+    assert_eq!((1, 1), part12(&memory, &RoundRobinFromMax, CycleDetection::HashMap));
+    assert_eq!((1, 1), part12(&memory, &RoundRobinFromMax, CycleDetection::Brent));
+}
+
+#[test]
+fn test_tree_matches_round_robin() {
+    let memory = vec![0, 2, 7, 0];
+    assert_eq!((5, 4), part12_tree(&memory));
+
+    let memory = vec![2, 0, 7, 0, 0, 31, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    assert_eq!(part12(&memory, &RoundRobinFromMax, CycleDetection::Brent), part12_tree(&memory));
+
+    let mut memory: MemoryBanks = (0..1000).map(|i| bank_weight(i) >> 20).collect();
+    let mut tree = BankTree::new(&memory);
+    for _ in 0..5000 {
+        RoundRobinFromMax.reallocate(&mut memory);
+        tree.reallocate();
+    }
+    assert_eq!(memory, tree.to_memory_banks());
+    assert!(tree == BankTree::new(&memory));
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn hash_map_bench(b: &mut Bencher) {
+    let memory = get_input();
+    b.iter(|| {
+        test::black_box(part12(&memory, &RoundRobinFromMax, CycleDetection::HashMap));
+    });
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn brent_bench(b: &mut Bencher) {
+    let memory = get_input();
+    b.iter(|| {
+        test::black_box(part12(&memory, &RoundRobinFromMax, CycleDetection::Brent));
+    });
+}
+
+#[cfg(feature = "bench")]
+fn large_memory_banks() -> MemoryBanks {
+    return (0..4096).map(|i| bank_weight(i) >> 24).collect();
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn large_round_robin_bench(b: &mut Bencher) {
+    let memory = large_memory_banks();
+    b.iter(|| {
+        let mut memory = memory.clone();
+        for _ in 0..1000 {
+            RoundRobinFromMax.reallocate(&mut memory);
+        }
+        test::black_box(memory);
+    });
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn large_tree_bench(b: &mut Bencher) {
+    let tree = BankTree::new(&large_memory_banks());
+    b.iter(|| {
+        let mut tree = tree.clone();
+        for _ in 0..1000 {
+            tree.reallocate();
+        }
+        test::black_box(tree.hash);
+    });
 }
 
 fn main() {
-    let (part1_answer, part2_answer) = part12();
+    let method = env::args().nth(1);
+    let strategy_name = env::args().nth(2);
+    let input = get_input();
+    let (part1_answer, part2_answer) = if method.as_deref() == Some("tree") {
+        if let Some(strategy_name) = strategy_name.filter(|name| name != "max") {
+            panic!("The tree method always uses the max redistribution strategy, got {}", strategy_name);
+        }
+        part12_tree(&input)
+    } else {
+        let method = match method.as_deref() {
+            None | Some("hashmap") => CycleDetection::HashMap,
+            Some("brent") => CycleDetection::Brent,
+            Some(other) => panic!("Unknown cycle detection method {}, expected hashmap, brent or tree", other),
+        };
+        let strategy = parse_strategy(strategy_name.as_deref().unwrap_or("max"));
+        part12(&input, strategy.as_ref(), method)
+    };
     println!("part1: {}", part1_answer);
     println!("part2: {}", part2_answer);
 }
@@ -1,16 +1,73 @@
-#![feature(entry_and_modify)]
+#![allow(clippy::needless_return)]
 
+use std::env;
+use std::fmt::Write;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::iter::FromIterator;
-use std::collections::HashMap;
+use std::fmt;
+use std::convert::TryFrom;
+use std::process;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 
+mod tower;
+
+use tower::{Tower, ProgramId};
+
 #[derive(Debug)]
 struct Disc<'a> {
     weight: u32,
     children: Vec<&'a str>,
-    parent: Option<&'a str>
+    // Every disc listing this one as a child, a valid tower has at most one
+    parents: Vec<&'a str>,
+    // Number of input lines for this disc, zero if it was only ever listed as a child
+    definitions: u32
+}
+
+#[derive(Debug, PartialEq)]
+enum TowerError<'a> {
+    // Listed as a child but never given its own line
+    MissingDisc { name: &'a str, parents: Vec<&'a str> },
+    DuplicateDisc { name: &'a str, definitions: u32 },
+    MultipleParents { name: &'a str, parents: Vec<&'a str> },
+    NoRoot,
+    MultipleRoots(Vec<&'a str>),
+    // Each disc is a child of the one before it, and the first is a child of the last
+    Cycle(Vec<&'a str>),
+    // The children's subtower weights differ but there is no single odd one out to fix
+    AmbiguousImbalance { name: &'a str, subtower_weights: Vec<u32> },
+    // The odd one out would need a weight below 1 to balance
+    NegativeWeight { name: &'a str, needed_change: i64 },
+    // Discs whose children are unbalanced in a way that changing a single weight can't explain
+    MultipleFaults { names: Vec<&'a str> }
+}
+
+impl<'a> fmt::Display for TowerError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TowerError::MissingDisc { name, ref parents } =>
+                write!(f, "{} is held by {} but has no weight", name, parents.join(", ")),
+            TowerError::DuplicateDisc { name, definitions } =>
+                write!(f, "{} is defined {} times", name, definitions),
+            TowerError::MultipleParents { name, ref parents } =>
+                write!(f, "{} is held by more than one disc: {}", name, parents.join(", ")),
+            TowerError::NoRoot => write!(f, "Every disc is held by another disc"),
+            TowerError::MultipleRoots(ref names) =>
+                write!(f, "More than one disc is not held by another disc: {}", names.join(", ")),
+            TowerError::Cycle(ref names) =>
+                write!(f, "Discs hold each other in a cycle: {} -> {}", names.join(" -> "), names[0]),
+            TowerError::AmbiguousImbalance { name, ref subtower_weights } => {
+                let weights: Vec<String> = subtower_weights.iter().map(|w| w.to_string()).collect();
+                write!(f, "{} is unbalanced but no single child can be fixed, subtower weights: {}", name, weights.join(", "))
+            }
+            TowerError::NegativeWeight { name, needed_change } =>
+                write!(f, "{} would need its weight changed by {}, which takes it below 1", name, needed_change),
+            TowerError::MultipleFaults { ref names } =>
+                write!(f, "More than one weight is wrong, imbalances at: {}", names.join(", ")),
+        }
+    }
 }
 
 fn get_input() -> String {
@@ -36,18 +93,19 @@ fn parse_input<'a>(input: &'a str) -> HashMap<&'a str, Disc<'a>> {
         word_iter.next();
 
         // Children are comma separated, so strip that off
-        let children = Vec::from_iter(word_iter.map(|x| x.trim_right_matches(',')));
+        let children = Vec::from_iter(word_iter.map(|x| x.trim_end_matches(',')));
 
         for child in children.iter() {
             match discs.entry(child) {
                 Entry::Occupied(mut v) => {
-                    v.get_mut().parent = Some(name);
+                    v.get_mut().parents.push(name);
                 }
                 Entry::Vacant(v) => {
                     v.insert(Disc {
                         weight: Default::default(),
                         children: Default::default(),
-                        parent: Some(name)
+                        parents: vec![name],
+                        definitions: 0
                     });
                 }
             }
@@ -56,13 +114,14 @@ fn parse_input<'a>(input: &'a str) -> HashMap<&'a str, Disc<'a>> {
             Entry::Occupied(mut v) => {
                 v.get_mut().weight = weight;
                 v.get_mut().children = children;
-                assert!(!v.get().parent.is_none());
+                v.get_mut().definitions += 1;
             }
             Entry::Vacant(v) => {
                 v.insert(Disc {
-                    weight: weight,
-                    children: children,
-                    parent: None
+                    weight,
+                    children,
+                    parents: Vec::new(),
+                    definitions: 1
                 });
             }
         }
@@ -71,57 +130,215 @@ fn parse_input<'a>(input: &'a str) -> HashMap<&'a str, Disc<'a>> {
     return discs;
 }
 
-fn get_root<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> Option<(&'a str, &Disc<'a>)> {
+fn get_root<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> Option<(&'a str, &'a Disc<'a>)> {
     for (name, disc) in discs.iter() {
-        if disc.parent.is_none() {
+        if disc.parents.is_empty() {
             return Some((name, disc));
         }
     }
     return None;
 }
 
-enum BalancedStatus {
-    Unbalanced(u32),
+fn sorted_names<'a, I: Iterator<Item = &'a str>>(names: I) -> Vec<&'a str> {
+    let mut result: Vec<&'a str> = names.collect();
+    result.sort();
+    return result;
+}
+
+fn find_cycles<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, path: &mut Vec<&'a str>,
+                   finished: &mut HashSet<&'a str>, errors: &mut Vec<TowerError<'a>>) {
+    if finished.contains(name) {
+        return;
+    }
+    if let Some(position) = path.iter().position(|&n| n == name) {
+        errors.push(TowerError::Cycle(path[position..].to_vec()));
+        return;
+    }
+
+    path.push(name);
+    for child_name in discs[name].children.iter() {
+        find_cycles(child_name, discs, path, finished, errors);
+    }
+    path.pop();
+    finished.insert(name);
+}
+
+// How a subtower's total weight changes once its wrong weight is fixed
+enum SubtowerFix {
+    Balanced,
+    Change(i64),
+    // The subtower is unbalanced in a way that has already been reported
+    Unknown
+}
+
+// Returns the total weight of the subtower and how fixing it changes that total, recording every
+// disc whose children can't be balanced by changing a single weight. Discs whose children are
+// unbalanced go in faults, and with a single wrong weight there is only ever one of them.
+fn find_ambiguous_imbalances<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, errors: &mut Vec<TowerError<'a>>,
+                                 faults: &mut Vec<&'a str>) -> (u32, SubtowerFix) {
+    let disc = &discs[name];
+    let mut subtower_weights = Vec::new();
+    let mut fixes = Vec::new();
+    for child_name in disc.children.iter() {
+        let (subtower_weight, fix) = find_ambiguous_imbalances(child_name, discs, errors, faults);
+        subtower_weights.push(subtower_weight);
+        fixes.push(fix);
+    }
+    let total_weight = disc.weight + subtower_weights.iter().sum::<u32>();
+
+    let unbalanced_children: Vec<usize> = (0..fixes.len()).filter(|&i| !matches!(fixes[i], SubtowerFix::Balanced)).collect();
+    if unbalanced_children.len() > 1 {
+        // Each of those subtowers has a wrong weight of its own
+        faults.push(name);
+        return (total_weight, SubtowerFix::Unknown);
+    }
+    // When a subtower is unbalanced the wrong weight is further down, this disc's children only
+    // look wrong because of it. Once it's fixed they all have to match.
+    if let Some(&unbalanced_index) = unbalanced_children.first() {
+        let change = match fixes[unbalanced_index] {
+            SubtowerFix::Change(change) => change,
+            _ => return (total_weight, SubtowerFix::Unknown)
+        };
+        let mut fixed_weights = subtower_weights.iter().map(|&weight| weight as i64).collect::<Vec<i64>>();
+        fixed_weights[unbalanced_index] += change;
+        if fixed_weights.windows(2).any(|pair| pair[0] != pair[1]) {
+            faults.push(name);
+            return (total_weight, SubtowerFix::Unknown);
+        }
+        return (total_weight, SubtowerFix::Change(change));
+    }
+
+    let mut weight_counts: HashMap<u32, usize> = HashMap::new();
+    for &weight in subtower_weights.iter() {
+        *weight_counts.entry(weight).or_insert(0) += 1;
+    }
+    if weight_counts.len() < 2 {
+        return (total_weight, SubtowerFix::Balanced);
+    }
+    faults.push(name);
+    let odd_weights: Vec<u32> = weight_counts.iter().filter(|&(_, &count)| count == 1).map(|(&weight, _)| weight).collect();
+    if weight_counts.len() != 2 || odd_weights.len() != 1 {
+        errors.push(TowerError::AmbiguousImbalance { name, subtower_weights: subtower_weights.clone() });
+        return (total_weight, SubtowerFix::Unknown);
+    }
+    let odd_weight = odd_weights[0];
+    let good_weight = *weight_counts.keys().find(|&&weight| weight != odd_weight).unwrap();
+    let odd_index = subtower_weights.iter().position(|&weight| weight == odd_weight).unwrap();
+    let odd_name = disc.children[odd_index];
+    let needed_change = good_weight as i64 - odd_weight as i64;
+    if discs[odd_name].weight as i64 + needed_change < 1 {
+        errors.push(TowerError::NegativeWeight { name: odd_name, needed_change });
+    }
+    return (total_weight, SubtowerFix::Change(needed_change));
+}
+
+// Checks that the discs form a single tree whose imbalance, if any, can be fixed by changing one
+// weight. Returns the name of the root if so.
+fn validate_tower<'a>(discs: &HashMap<&'a str, Disc<'a>>) -> Result<&'a str, Vec<TowerError<'a>>> {
+    let root = validate_structure(discs)?;
+
+    let mut errors = Vec::new();
+    let mut faults = Vec::new();
+    find_ambiguous_imbalances(root, discs, &mut errors, &mut faults);
+    if faults.len() > 1 {
+        errors.push(TowerError::MultipleFaults { names: faults });
+    }
+    if errors.is_empty() {
+        return Ok(root);
+    }
+    return Err(errors);
+}
+
+// Checks that the discs form a single tree, returning the name of the root if so
+fn validate_structure<'a>(discs: &HashMap<&'a str, Disc<'a>>) -> Result<&'a str, Vec<TowerError<'a>>> {
+    let mut errors = Vec::new();
+
+    for name in sorted_names(discs.keys().cloned()) {
+        let disc = &discs[name];
+        if disc.definitions == 0 {
+            errors.push(TowerError::MissingDisc { name, parents: disc.parents.clone() });
+        } else if disc.definitions > 1 {
+            errors.push(TowerError::DuplicateDisc { name, definitions: disc.definitions });
+        }
+        if disc.parents.len() > 1 {
+            errors.push(TowerError::MultipleParents { name, parents: disc.parents.clone() });
+        }
+    }
+
+    let roots = sorted_names(discs.iter().filter(|&(_, disc)| disc.parents.is_empty()).map(|(&name, _)| name));
+    match roots.len() {
+        0 => errors.push(TowerError::NoRoot),
+        1 => {}
+        _ => errors.push(TowerError::MultipleRoots(roots.clone())),
+    }
+
+    let mut finished = HashSet::new();
+    for name in sorted_names(discs.keys().cloned()) {
+        find_cycles(name, discs, &mut Vec::new(), &mut finished, &mut errors);
+    }
+
+    if errors.is_empty() {
+        return Ok(roots[0]);
+    }
+    return Err(errors);
+}
+
+enum BalancedStatus<'a> {
+    // The name of the disc with the wrong weight and the weight it should have
+    Unbalanced(&'a str, u32),
     Balanced(u32)
 }
 
-fn is_tower_balanced<'a>(base_disc: &'a Disc<'a>, discs: &HashMap<&'a str, Disc<'a>>) -> BalancedStatus {
-    let mut weights: HashMap<u32, (u32, u32)> = HashMap::new();
+fn is_tower_balanced<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>) -> Result<BalancedStatus<'a>, TowerError<'a>> {
+    let base_disc = &discs[name];
+    let mut weights: HashMap<u32, (u32, u32, &'a str)> = HashMap::new();
+    let mut subtower_weights = Vec::new();
 
-    let mut total_weight = base_disc.weight;;
+    let mut total_weight = base_disc.weight;
     for child_name in base_disc.children.iter() {
         let child = discs.get(child_name).expect("Child does not exist???");
-        match is_tower_balanced(child, discs) {
-            BalancedStatus::Unbalanced(needed_weight) => {
-                return BalancedStatus::Unbalanced(needed_weight)
+        match is_tower_balanced(child_name, discs)? {
+            BalancedStatus::Unbalanced(name, needed_weight) => {
+                return Ok(BalancedStatus::Unbalanced(name, needed_weight))
             }
             BalancedStatus::Balanced(subtower_weight) => {
                 total_weight += subtower_weight;
+                subtower_weights.push(subtower_weight);
                 weights.entry(subtower_weight)
-                    .and_modify(|&mut (ref mut count, _)| *count += 1)
-                    .or_insert((1, child.weight));
+                    .and_modify(|&mut (ref mut count, _, _)| *count += 1)
+                    .or_insert((1, child.weight, *child_name));
             }
         }
     }
 
     if weights.len() > 1 {
-        assert!(weights.len() == 2);
+        // Exactly one child must be the odd one out for a single change to fix it
+        let odd_count = weights.values().filter(|&&(count, _, _)| count == 1).count();
+        if weights.len() != 2 || odd_count != 1 {
+            return Err(TowerError::AmbiguousImbalance { name, subtower_weights });
+        }
         let mut good_weight = 0;
         let mut bad_weight = 0;
         let mut bad_child_weight = 0;
-        for (&total_weight, &(count, child_weight)) in weights.iter() {
+        let mut bad_child_name = "";
+        for (&total_weight, &(count, child_weight, child_name)) in weights.iter() {
             if count == 1 {
                 bad_weight = total_weight;
-                bad_child_weight = child_weight
+                bad_child_weight = child_weight;
+                bad_child_name = child_name;
             } else {
                 good_weight = total_weight;
             }
         }
 
-        return BalancedStatus::Unbalanced(good_weight - (bad_weight - bad_child_weight));
+        let needed_change = good_weight as i64 - bad_weight as i64;
+        if bad_child_weight as i64 + needed_change < 1 {
+            return Err(TowerError::NegativeWeight { name: bad_child_name, needed_change });
+        }
+        return Ok(BalancedStatus::Unbalanced(bad_child_name, (bad_child_weight as i64 + needed_change) as u32));
     }
 
-    return BalancedStatus::Balanced(total_weight);
+    return Ok(BalancedStatus::Balanced(total_weight));
 }
 
 fn part1<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> &'a str {
@@ -131,17 +348,614 @@ fn part1<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> &'a str {
     }
 }
 
-fn part2<'a>(discs: &HashMap<&'a str, Disc<'a>>) -> u32 {
-    let (_, root_disc) = get_root(discs).expect("No root?");
-    return match is_tower_balanced(root_disc, discs) {
-        BalancedStatus::Unbalanced(expected_weight) => expected_weight,
-        BalancedStatus::Balanced(_) => 0
+fn part2<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> Result<u32, TowerError<'a>> {
+    let (root_name, _) = get_root(discs).expect("No root?");
+    return match is_tower_balanced(root_name, discs)? {
+        BalancedStatus::Unbalanced(_, expected_weight) => Ok(expected_weight),
+        BalancedStatus::Balanced(_) => Ok(0)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct WeightChange<'a> {
+    name: &'a str,
+    old_weight: u32,
+    new_weight: u32
+}
+
+// The fewest weight changes needed to balance a subtower, as a function of the total weight
+// the subtower ends up with
+struct RebalanceCosts {
+    // Totals reachable without changing this disc's own weight
+    special_totals: HashMap<u64, u32>,
+    // (min_total, cost) pairs sorted by min_total with decreasing costs: any total of at least
+    // min_total is reachable for cost by changing this disc's own weight. Like the puzzle's
+    // weights, a changed weight is always at least 1.
+    changed_weight_costs: Vec<(u64, u32)>
+}
+
+impl RebalanceCosts {
+    fn cost_at(&self, total: u64) -> Option<u32> {
+        let changed_weight_cost = self.changed_weight_costs.iter().take_while(|&&(min_total, _)| min_total <= total).last().map(|&(_, cost)| cost);
+        return match (self.special_totals.get(&total), changed_weight_cost) {
+            (Some(&special), Some(changed)) => Some(::std::cmp::min(special, changed)),
+            (Some(&special), None) => Some(special),
+            (None, changed) => changed
+        };
+    }
+
+    // Every total where the cost can drop
+    fn breakpoints(&self) -> Vec<u64> {
+        let mut result: Vec<u64> = self.special_totals.keys().cloned().collect();
+        result.extend(self.changed_weight_costs.iter().map(|&(min_total, _)| min_total));
+        return result;
+    }
+}
+
+// Fewest changes for every child of the disc to end up with subtower_total, if possible
+fn children_cost_at<'a>(disc: &Disc<'a>, costs: &HashMap<&'a str, RebalanceCosts>, subtower_total: u64) -> Option<u32> {
+    let mut result = 0;
+    for child_name in disc.children.iter() {
+        result += costs[child_name].cost_at(subtower_total)?;
+    }
+    return Some(result);
+}
+
+// The children's costs only change at their breakpoints, so those are the only subtower totals
+// worth trying
+fn candidate_subtower_totals<'a>(disc: &Disc<'a>, costs: &HashMap<&'a str, RebalanceCosts>) -> Vec<u64> {
+    let mut result: Vec<u64> = disc.children.iter().flat_map(|child_name| costs[child_name].breakpoints()).collect();
+    result.sort();
+    result.dedup();
+    return result;
+}
+
+fn compute_rebalance_costs<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, costs: &mut HashMap<&'a str, RebalanceCosts>) {
+    let disc = &discs[name];
+    for child_name in disc.children.iter() {
+        compute_rebalance_costs(child_name, discs, costs);
+    }
+
+    let child_count = disc.children.len() as u64;
+    let mut special_totals = HashMap::new();
+    let mut changed_weight_costs: Vec<(u64, u32)> = Vec::new();
+    if child_count == 0 {
+        special_totals.insert(disc.weight as u64, 0);
+        changed_weight_costs.push((1, 1));
+    }
+    for subtower_total in candidate_subtower_totals(disc, costs) {
+        if let Some(cost) = children_cost_at(disc, costs, subtower_total) {
+            special_totals.insert(disc.weight as u64 + child_count * subtower_total, cost);
+            if changed_weight_costs.last().is_none_or(|&(_, last_cost)| cost + 1 < last_cost) {
+                changed_weight_costs.push((child_count * subtower_total + 1, cost + 1));
+            }
+        }
+    }
+
+    costs.insert(name, RebalanceCosts { special_totals, changed_weight_costs });
+}
+
+// Gives the subtower the requested total weight with as few changes as possible
+fn rebalance_to_total<'a>(name: &'a str, total: u64, discs: &HashMap<&'a str, Disc<'a>>,
+                          costs: &HashMap<&'a str, RebalanceCosts>, changes: &mut Vec<WeightChange<'a>>) {
+    let disc = &discs[name];
+    let child_count = disc.children.len() as u64;
+
+    // Keeping this disc's weight pins down the children's subtower total
+    let mut best: Option<(u32, u64)> = None;
+    if total >= disc.weight as u64 {
+        let remaining = total - disc.weight as u64;
+        if child_count == 0 && remaining == 0 {
+            best = Some((0, 0));
+        } else if child_count > 0 && remaining.is_multiple_of(child_count) {
+            best = children_cost_at(disc, costs, remaining / child_count).map(|cost| (cost, remaining / child_count));
+        }
+    }
+
+    // Otherwise pick whatever children's total is cheapest and make up the difference here
+    let mut changed_weight = false;
+    let candidates = if child_count == 0 { vec![0] } else { candidate_subtower_totals(disc, costs) };
+    for subtower_total in candidates {
+        // Leaves room for a new weight of at least 1
+        if child_count * subtower_total >= total {
+            continue;
+        }
+        if let Some(cost) = children_cost_at(disc, costs, subtower_total) {
+            if best.is_none_or(|(min, _)| cost + 1 < min) {
+                best = Some((cost + 1, subtower_total));
+                changed_weight = true;
+            }
+        }
+    }
+
+    let (_, subtower_total) = best.expect("Requested total is not reachable");
+    if changed_weight {
+        let new_weight = total - child_count * subtower_total;
+        changes.push(WeightChange {
+            name,
+            old_weight: disc.weight,
+            new_weight: u32::try_from(new_weight).expect("New weight is too big")
+        });
+    }
+    for child_name in disc.children.iter() {
+        rebalance_to_total(child_name, subtower_total, discs, costs, changes);
+    }
+}
+
+// Finds the fewest weight changes that balance every disc in the tower, for towers with any
+// number of wrong weights
+fn rebalance_tower<'a>(root_name: &'a str, discs: &HashMap<&'a str, Disc<'a>>) -> Vec<WeightChange<'a>> {
+    let mut costs = HashMap::new();
+    compute_rebalance_costs(root_name, discs, &mut costs);
+
+    let root_costs = &costs[root_name];
+    let mut totals = root_costs.breakpoints();
+    totals.sort();
+    let best_total = totals.into_iter().min_by_key(|&total| root_costs.cost_at(total).unwrap_or(u32::MAX)).unwrap();
+
+    let mut changes = Vec::new();
+    rebalance_to_total(root_name, best_total, discs, &costs, &mut changes);
+    return changes;
+}
+
+fn subtree_weight<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, weights: &mut HashMap<&'a str, u32>) -> u32 {
+    let disc = &discs[name];
+    let mut total_weight = disc.weight;
+    for child_name in disc.children.iter() {
+        total_weight += subtree_weight(child_name, discs, weights);
+    }
+    weights.insert(name, total_weight);
+    return total_weight;
+}
+
+fn escape_string(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+fn write_dot_disc<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, weights: &HashMap<&'a str, u32>,
+                      unbalanced: Option<&'a str>, output: &mut String) {
+    let disc = &discs[name];
+    let escaped_name = escape_string(name);
+    write!(output, "    \"{}\" [label=\"{}\\nweight: {}\\nsubtree: {}\"", escaped_name, escaped_name, disc.weight, weights[name]).unwrap();
+    if unbalanced == Some(name) {
+        output.push_str(", style=filled, fillcolor=red");
+    }
+    output.push_str("];\n");
+    for child_name in disc.children.iter() {
+        writeln!(output, "    \"{}\" -> \"{}\";", escaped_name, escape_string(child_name)).unwrap();
+        write_dot_disc(child_name, discs, weights, unbalanced, output);
+    }
+}
+
+// Graphviz digraph of the tower from the root down, the disc with the wrong weight filled in red
+fn to_dot<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> String {
+    let (root_name, _) = get_root(discs).expect("No root?");
+    let mut weights = HashMap::new();
+    subtree_weight(root_name, discs, &mut weights);
+    // Nothing is highlighted when no single disc is to blame
+    let unbalanced = match is_tower_balanced(root_name, discs) {
+        Ok(BalancedStatus::Unbalanced(name, _)) => Some(name),
+        Ok(BalancedStatus::Balanced(_)) | Err(_) => None
+    };
+
+    let mut output = String::new();
+    output.push_str("digraph tower {\n    node [shape=box];\n");
+    write_dot_disc(root_name, discs, &weights, unbalanced, &mut output);
+    output.push_str("}\n");
+    return output;
+}
+
+fn write_json_disc<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>, weights: &HashMap<&'a str, u32>,
+                       unbalanced: Option<&'a str>, output: &mut String) {
+    let disc = &discs[name];
+    write!(output, "{{\"name\":\"{}\",\"weight\":{},\"subtree_weight\":{},\"unbalanced\":{},\"children\":[",
+           escape_string(name), disc.weight, weights[name], unbalanced == Some(name)).unwrap();
+    for (i, child_name) in disc.children.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        write_json_disc(child_name, discs, weights, unbalanced, output);
+    }
+    output.push_str("]}");
+}
+
+// Nested JSON objects of the tower from the root down
+fn to_json<'a>(discs: &'a HashMap<&'a str, Disc<'a>>) -> String {
+    let (root_name, _) = get_root(discs).expect("No root?");
+    let mut weights = HashMap::new();
+    subtree_weight(root_name, discs, &mut weights);
+    // Nothing is highlighted when no single disc is to blame
+    let unbalanced = match is_tower_balanced(root_name, discs) {
+        Ok(BalancedStatus::Unbalanced(name, _)) => Some(name),
+        Ok(BalancedStatus::Balanced(_)) | Err(_) => None
+    };
+
+    let mut output = String::new();
+    write_json_disc(root_name, discs, &weights, unbalanced, &mut output);
+    output.push('\n');
+    return output;
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "pbga (66)
+xhth (57)
+ebii (61)
+havc (66)
+ktlj (57)
+fwft (72) -> ktlj, cntj, xhth
+qoyq (66)
+padx (45) -> pbga, havc, qoyq
+tknk (41) -> ugml, padx, fwft
+jptl (61)
+ugml (68) -> gyxo, ebii, jptl
+gyxo (61)
+cntj (57)";
+
+#[test]
+fn test_export() {
+    let discs = parse_input(EXAMPLE_INPUT);
+    let dot = to_dot(&discs);
+    assert!(dot.contains("\"ugml\" [label=\"ugml\\nweight: 68\\nsubtree: 251\", style=filled, fillcolor=red];"));
+    assert!(dot.contains("\"tknk\" -> \"padx\";"));
+    assert_eq!(13, dot.matches("label=").count());
+
+    let json = to_json(&discs);
+    assert!(json.starts_with("{\"name\":\"tknk\",\"weight\":41,\"subtree_weight\":778,\"unbalanced\":false,"));
+    assert!(json.contains("{\"name\":\"pbga\",\"weight\":66,\"subtree_weight\":66,\"unbalanced\":false,\"children\":[]}"));
+}
+
+#[test]
+fn test_validate_tower() {
+    let discs = parse_input(EXAMPLE_INPUT);
+    assert_eq!(Ok("tknk"), validate_tower(&discs));
+
+    let discs = parse_input("a (1) -> b, c\nb (2)\nd (3) -> a\ne (4)");
+    assert_eq!(Err(vec![
+        TowerError::MissingDisc { name: "c", parents: vec!["a"] },
+        TowerError::MultipleRoots(vec!["d", "e"]),
+    ]), validate_tower(&discs));
+
+    let discs = parse_input("a (1) -> b\nb (2) -> c\nc (3) -> a, d\nd (4)");
+    assert_eq!(Err(vec![
+        TowerError::NoRoot,
+        TowerError::Cycle(vec!["a", "b", "c"]),
+    ]), validate_tower(&discs));
+
+    let discs = parse_input("a (1) -> b, c\nb (2)\nc (3)\nd (4) -> a, e, f\ne (6)\nf (6)");
+    assert_eq!(Err(vec![
+        TowerError::AmbiguousImbalance { name: "a", subtower_weights: vec![2, 3] },
+    ]), validate_tower(&discs));
+
+    let discs = parse_input("a (1) -> b, c, d\nb (2)\nc (9)\nd (9)\nb (2)");
+    assert_eq!(Err(vec![
+        TowerError::DuplicateDisc { name: "b", definitions: 2 },
+    ]), validate_tower(&discs));
+
+    let discs = parse_input("a (1) -> b, c, d\nb (2)\nc (9)\nd (9)\ne (1) -> b");
+    let errors = validate_tower(&discs).unwrap_err();
+    assert!(errors.contains(&TowerError::MultipleParents { name: "b", parents: vec!["a", "e"] }));
+
+    let discs = parse_input("a (1) -> b, c, d\nb (2)\nc (9)\nd (9)\n");
+    assert_eq!(Ok("a"), validate_tower(&discs));
+    let discs = parse_input("a (1) -> b, c, d\nb (20)\nc (9)\nd (9)\n");
+    assert_eq!(Ok("a"), validate_tower(&discs));
+    let discs = parse_input("a (1) -> b, c, d\nb (2) -> e\nc (3)\nd (3)\ne (10)");
+    assert_eq!(Err(vec![
+        TowerError::NegativeWeight { name: "b", needed_change: -9 },
+    ]), validate_tower(&discs));
+}
+
+#[test]
+fn test_validate_nested_imbalance() {
+    // a3 is wrong, which also makes a heavier than b, but that isn't a second problem
+    let discs = parse_input("r (1) -> a, b\na (1) -> a1, a2, a3\na1 (1)\na2 (1)\na3 (2)\n\
+                             b (1) -> b1, b2, b3\nb1 (1)\nb2 (1)\nb3 (1)");
+    assert_eq!(Ok("r"), validate_tower(&discs));
+    assert_eq!(Ok(1), part2(&discs));
+
+    // Without validating first the same problems come back as errors
+    let discs = parse_input("a (1) -> b, c\nb (2)\nc (3)");
+    assert_eq!(Err(TowerError::AmbiguousImbalance { name: "a", subtower_weights: vec![2, 3] }), part2(&discs));
+    let discs = parse_input("a (1) -> b, c, d\nb (2) -> e\nc (3)\nd (3)\ne (10)");
+    assert_eq!(Err(TowerError::NegativeWeight { name: "b", needed_change: -9 }), part2(&discs));
+}
+
+#[test]
+fn test_validate_multiple_faults() {
+    // a3 and b3 are both wrong, and fixing either one leaves the other
+    let discs = parse_input("r (1) -> a, b\na (1) -> a1, a2, a3\na1 (2)\na2 (2)\na3 (3)\n\
+                             b (1) -> b1, b2, b3\nb1 (2)\nb2 (2)\nb3 (4)");
+    assert_eq!(Err(vec![
+        TowerError::MultipleFaults { names: vec!["a", "b", "r"] },
+    ]), validate_tower(&discs));
+
+    // a3 is wrong, and so is c, which fixing a3 doesn't explain
+    let discs = parse_input("r (1) -> a, b, c\na (1) -> a1, a2, a3\na1 (1)\na2 (1)\na3 (2)\nb (4)\nc (5)");
+    assert_eq!(Err(vec![
+        TowerError::MultipleFaults { names: vec!["a", "r"] },
+    ]), validate_tower(&discs));
+
+    // Balancing c would take its weight to exactly 0
+    let discs = parse_input("a (1) -> b, c, d\nb (2)\nc (3) -> e\nd (2)\ne (2)");
+    assert_eq!(Err(vec![
+        TowerError::NegativeWeight { name: "c", needed_change: -3 },
+    ]), validate_tower(&discs));
+    assert_eq!(Err(TowerError::NegativeWeight { name: "c", needed_change: -3 }), part2(&discs));
+}
+
+#[cfg(test)]
+fn apply_changes<'a>(discs: &mut HashMap<&'a str, Disc<'a>>, changes: &[WeightChange<'a>]) {
+    for change in changes.iter() {
+        let disc = discs.get_mut(change.name).unwrap();
+        assert_eq!(change.old_weight, disc.weight);
+        disc.weight = change.new_weight;
+    }
+}
+
+#[cfg(test)]
+fn is_fully_balanced<'a>(name: &'a str, discs: &HashMap<&'a str, Disc<'a>>) -> Option<u64> {
+    let disc = &discs[name];
+    let mut subtower_weights = Vec::new();
+    for child_name in disc.children.iter() {
+        subtower_weights.push(is_fully_balanced(child_name, discs)?);
+    }
+    if subtower_weights.windows(2).any(|pair| pair[0] != pair[1]) {
+        return None;
+    }
+    return Some(disc.weight as u64 + subtower_weights.iter().sum::<u64>());
+}
+
+#[test]
+fn test_rebalance_tower() {
+    let discs = parse_input(EXAMPLE_INPUT);
+    assert_eq!(vec![WeightChange { name: "ugml", old_weight: 68, new_weight: 60 }], rebalance_tower("tknk", &discs));
+
+    // b3 one level down and c right below the root are wrong
+    let input = "r (5) -> a, b, c
+a (10) -> a1, a2, a3
+a1 (3)
+a2 (3)
+a3 (3)
+b (10) -> b1, b2, b3
+b1 (3)
+b2 (3)
+b3 (7)
+c (14) -> c1, c2, c3
+c1 (3)
+c2 (3)
+c3 (3)";
+    let mut discs = parse_input(input);
+    let mut changes = rebalance_tower("r", &discs);
+    changes.sort_by_key(|change| change.name);
+    assert_eq!(vec![
+        WeightChange { name: "b3", old_weight: 7, new_weight: 3 },
+        WeightChange { name: "c", old_weight: 14, new_weight: 10 },
+    ], changes);
+    apply_changes(&mut discs, &changes);
+    assert!(is_fully_balanced("r", &discs).is_some());
+
+    // x3 two levels down, b2 one level down and c right below the root are wrong
+    let input = "r (1) -> a, b, c
+a (2) -> a1, a2
+a1 (5) -> x1, x2, x3
+x1 (1)
+x2 (1)
+x3 (4)
+a2 (8)
+b (2) -> b1, b2
+b1 (8)
+b2 (9)
+c (7) -> c1, c2
+c1 (8)
+c2 (8)";
+    let mut discs = parse_input(input);
+    let mut changes = rebalance_tower("r", &discs);
+    changes.sort_by_key(|change| change.name);
+    assert_eq!(vec![
+        WeightChange { name: "b2", old_weight: 9, new_weight: 8 },
+        WeightChange { name: "c", old_weight: 7, new_weight: 2 },
+        WeightChange { name: "x3", old_weight: 4, new_weight: 1 },
+    ], changes);
+    apply_changes(&mut discs, &changes);
+    assert!(is_fully_balanced("r", &discs).is_some());
+
+    // Dropping c to 0 would be a single change, but weights stay positive so a and b go up
+    let input = "r (1) -> a, b, c
+a (4)
+b (4)
+c (1) -> c1, c2
+c1 (2)
+c2 (2)";
+    let mut discs = parse_input(input);
+    let mut changes = rebalance_tower("r", &discs);
+    changes.sort_by_key(|change| change.name);
+    assert_eq!(vec![
+        WeightChange { name: "a", old_weight: 4, new_weight: 5 },
+        WeightChange { name: "b", old_weight: 4, new_weight: 5 },
+    ], changes);
+    apply_changes(&mut discs, &changes);
+    assert!(is_fully_balanced("r", &discs).is_some());
+}
+
+#[test]
+fn test_rebalance_random_towers() {
+    // Builds balanced towers by copying the first child's subtower for its siblings, then
+    // breaks a few weights and checks the fix is balanced and no bigger than the breakage
+    struct Node {
+        weight: u32,
+        children: Vec<usize>
+    }
+
+    fn next_random(state: &mut u64) -> u32 {
+        *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        return (*state >> 33) as u32;
+    }
+
+    fn copy_node(index: usize, nodes: &mut Vec<Node>) -> usize {
+        let children: Vec<usize> = nodes[index].children.clone().into_iter().map(|child| copy_node(child, nodes)).collect();
+        nodes.push(Node { weight: nodes[index].weight, children });
+        return nodes.len() - 1;
+    }
+
+    fn generate_node(depth: u32, state: &mut u64, nodes: &mut Vec<Node>) -> usize {
+        let child_count = if depth == 0 { 0 } else { next_random(state) % 4 };
+        let mut children = Vec::new();
+        if child_count > 0 {
+            let first = generate_node(depth - 1, state, nodes);
+            children.push(first);
+            for _ in 1..child_count {
+                children.push(copy_node(first, nodes));
+            }
+        }
+        nodes.push(Node { weight: 1 + next_random(state) % 100, children });
+        return nodes.len() - 1;
+    }
+
+    let mut state: u64 = 0xdeadbeef;
+    for _ in 0..200 {
+        let mut nodes = Vec::new();
+        let root = generate_node(4, &mut state, &mut nodes);
+        let fault_count = 1 + next_random(&mut state) % 3;
+        for _ in 0..fault_count {
+            let index = next_random(&mut state) as usize % nodes.len();
+            nodes[index].weight += 1 + next_random(&mut state) % 20;
+        }
+
+        let mut input = String::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let children: Vec<String> = node.children.iter().map(|child| format!("d{}", child)).collect();
+            input.push_str(&format!("d{} ({}) -> {}\n", i, node.weight, children.join(", ")));
+        }
+        let root_name = format!("d{}", root);
+        let mut discs = parse_input(&input);
+        let changes = rebalance_tower(&root_name, &discs);
+        assert!(changes.len() <= fault_count as usize);
+        assert!(changes.iter().all(|change| change.new_weight >= 1));
+        apply_changes(&mut discs, &changes);
+        assert!(is_fully_balanced(&root_name, &discs).is_some());
+    }
+}
+
+fn lookup_program(tower: &Tower, name: Option<&str>) -> Result<ProgramId, String> {
+    let name = name.ok_or_else(|| "Expected program name".to_string())?;
+    return tower.id(name).ok_or_else(|| format!("Unknown program {}", name));
+}
+
+fn parse_weight(weight: Option<&str>) -> Result<u32, String> {
+    return weight.ok_or_else(|| "Expected weight".to_string())?.parse::<u32>().map_err(|e| e.to_string());
+}
+
+fn describe_program(tower: &Tower, id: ProgramId) -> String {
+    let parent = match tower.parent(id).unwrap() {
+        Some(parent) => tower.name(parent).unwrap().to_string(),
+        None => "-".to_string()
+    };
+    let children: Vec<&str> = tower.children(id).unwrap().iter().map(|&child| tower.name(child).unwrap()).collect();
+    return format!("{} ({}) subtower: {} parent: {} children: {}", tower.name(id).unwrap(), tower.weight(id).unwrap(),
+                   tower.subtower_weight(id).unwrap(), parent, children.join(", "));
+}
+
+fn run_edit_command(tower: &mut Tower, line: &str) -> Result<Option<String>, String> {
+    let mut word_iter = line.split_whitespace();
+    match word_iter.next() {
+        None => {}
+        Some("add") => {
+            let name = word_iter.next().ok_or_else(|| "Expected program name".to_string())?;
+            let weight = parse_weight(word_iter.next())?;
+            let parent = match word_iter.next() {
+                Some(parent_name) => Some(lookup_program(tower, Some(parent_name))?),
+                None => None
+            };
+            tower.add_program(name, weight, parent).map_err(|e| e.to_string())?;
+        }
+        Some("remove") => {
+            let id = lookup_program(tower, word_iter.next())?;
+            tower.remove_program(id).map_err(|e| e.to_string())?;
+        }
+        Some("weight") => {
+            let id = lookup_program(tower, word_iter.next())?;
+            let weight = parse_weight(word_iter.next())?;
+            tower.set_weight(id, weight).map_err(|e| e.to_string())?;
+        }
+        Some("move") => {
+            let id = lookup_program(tower, word_iter.next())?;
+            let new_parent = match word_iter.next() {
+                Some("-") => None,
+                parent_name => Some(lookup_program(tower, parent_name)?)
+            };
+            tower.reparent(id, new_parent).map_err(|e| e.to_string())?;
+        }
+        Some("show") => {
+            let id = lookup_program(tower, word_iter.next())?;
+            return Ok(Some(describe_program(tower, id)));
+        }
+        Some("roots") => {
+            let roots: Vec<String> = tower.roots().into_iter().map(|id| describe_program(tower, id)).collect();
+            return Ok(Some(roots.join("\n")));
+        }
+        Some(other) => return Err(format!("Unknown command {}, expected add, remove, weight, move, show or roots", other)),
+    }
+    return Ok(None);
+}
+
+#[test]
+fn test_edit_weights() {
+    let discs = parse_input("r (1) -> a\na (2)");
+    let mut tower = Tower::from_discs("r", &discs);
+    assert_eq!(Ok(None), run_edit_command(&mut tower, "weight a 3"));
+    assert_eq!(Some("a (3) subtower: 3 parent: r children: ".to_string()), run_edit_command(&mut tower, "show a").unwrap());
+    assert_eq!(Err("Weight must be at least 1".to_string()), run_edit_command(&mut tower, "weight a 0"));
+    assert_eq!(Err("Weight must be at least 1".to_string()), run_edit_command(&mut tower, "add b 0 r"));
+    assert_eq!(Some("r (1) subtower: 4 parent: - children: a".to_string()), run_edit_command(&mut tower, "roots").unwrap());
+}
+
+// Loads the tower into a Tower and applies edit commands read from stdin, one per line
+fn edit_tower(root_name: &str, discs: &HashMap<&str, Disc>) {
+    let mut tower = Tower::from_discs(root_name, discs);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        match run_edit_command(&mut tower, &line.expect("Could not read stdin")) {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => {}
+            Err(error) => eprintln!("{}", error),
+        }
     }
 }
 
 fn main() {
     let input = get_input();
     let discs = parse_input(input.as_str());
-    println!("Part 1: {}", part1(&discs));
-    println!("Part 2: {}", part2(&discs));
+    let mode = env::args().nth(1);
+    // Rebalancing and editing handle any number of wrong weights, so only need the tree itself to be valid
+    let validation = if mode.as_deref() == Some("rebalance") || mode.as_deref() == Some("edit") { validate_structure(&discs) } else { validate_tower(&discs) };
+    let root_name = match validation {
+        Ok(root_name) => root_name,
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("{}", error);
+            }
+            process::exit(1);
+        }
+    };
+    match mode.as_deref() {
+        None => {
+            println!("Part 1: {}", part1(&discs));
+            match part2(&discs) {
+                Ok(weight) => println!("Part 2: {}", weight),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("dot") => print!("{}", to_dot(&discs)),
+        Some("json") => print!("{}", to_json(&discs)),
+        Some("rebalance") => {
+            for change in rebalance_tower(root_name, &discs) {
+                println!("{}: {} -> {}", change.name, change.old_weight, change.new_weight);
+            }
+        }
+        Some("edit") => edit_tower(root_name, &discs),
+        Some(other) => panic!("Unknown mode {}, expected dot, json, rebalance or edit", other),
+    }
 }
@@ -0,0 +1,262 @@
+// An owned version of the tower that can be kept around and edited after the input is gone.
+// Programs live in an arena indexed by ProgramId, with names interned so each program's id
+// never changes, and every program caches the weight of its subtower.
+
+use std::fmt;
+use std::collections::HashMap;
+
+use super::Disc;
+
+pub type ProgramId = usize;
+
+#[derive(Debug)]
+struct Program {
+    weight: u32,
+    parent: Option<ProgramId>,
+    children: Vec<ProgramId>,
+    // Own weight plus the subtower weight of every child
+    subtower_weight: u64
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TowerEditError {
+    UnknownProgram(ProgramId),
+    DuplicateName(String),
+    // Only programs without children can be removed, move the children somewhere else first
+    HasChildren(ProgramId),
+    // The new parent is the program itself or one of the programs it holds
+    WouldCreateCycle { id: ProgramId, new_parent: ProgramId },
+    // Like the puzzle's, every weight is at least 1
+    ZeroWeight
+}
+
+impl fmt::Display for TowerEditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TowerEditError::UnknownProgram(id) => write!(f, "There is no program with id {}", id),
+            TowerEditError::DuplicateName(ref name) => write!(f, "There is already a program named {}", name),
+            TowerEditError::HasChildren(id) => write!(f, "Program {} still holds other programs", id),
+            TowerEditError::WouldCreateCycle { id, new_parent } =>
+                write!(f, "Program {} can't be held by {}, which it holds itself", id, new_parent),
+            TowerEditError::ZeroWeight => write!(f, "Weight must be at least 1"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Tower {
+    names: Vec<String>,
+    name_ids: HashMap<String, ProgramId>,
+    // Indexed by ProgramId, None once a program has been removed
+    programs: Vec<Option<Program>>
+}
+
+impl Tower {
+    pub fn new() -> Tower {
+        return Default::default();
+    }
+
+    // Copies the discs, which must already be known to form a tree with no weights of 0, starting
+    // from the root
+    pub fn from_discs<'a>(root_name: &'a str, discs: &HashMap<&'a str, Disc<'a>>) -> Tower {
+        let mut result = Tower::new();
+        result.add_disc(root_name, None, discs);
+        return result;
+    }
+
+    fn add_disc<'a>(&mut self, name: &'a str, parent: Option<ProgramId>, discs: &HashMap<&'a str, Disc<'a>>) {
+        let disc = &discs[name];
+        let id = self.add_program(name, disc.weight, parent).expect("Discs are not a tree or have a weight of 0");
+        for child_name in disc.children.iter() {
+            self.add_disc(child_name, Some(id), discs);
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<ProgramId> {
+        return self.name_ids.get(name).cloned().filter(|&id| self.programs[id].is_some());
+    }
+
+    pub fn name(&self, id: ProgramId) -> Result<&str, TowerEditError> {
+        self.program(id)?;
+        return Ok(&self.names[id]);
+    }
+
+    pub fn weight(&self, id: ProgramId) -> Result<u32, TowerEditError> {
+        return self.program(id).map(|program| program.weight);
+    }
+
+    pub fn subtower_weight(&self, id: ProgramId) -> Result<u64, TowerEditError> {
+        return self.program(id).map(|program| program.subtower_weight);
+    }
+
+    pub fn parent(&self, id: ProgramId) -> Result<Option<ProgramId>, TowerEditError> {
+        return self.program(id).map(|program| program.parent);
+    }
+
+    pub fn children(&self, id: ProgramId) -> Result<&[ProgramId], TowerEditError> {
+        return self.program(id).map(|program| program.children.as_slice());
+    }
+
+    // Programs not held by any other program, a single tower has exactly one
+    pub fn roots(&self) -> Vec<ProgramId> {
+        return (0..self.programs.len()).filter(|&id| {
+            self.programs[id].as_ref().is_some_and(|program| program.parent.is_none())
+        }).collect();
+    }
+
+    pub fn add_program(&mut self, name: &str, weight: u32, parent: Option<ProgramId>) -> Result<ProgramId, TowerEditError> {
+        if self.id(name).is_some() {
+            return Err(TowerEditError::DuplicateName(name.to_string()));
+        }
+        if weight == 0 {
+            return Err(TowerEditError::ZeroWeight);
+        }
+        if let Some(parent) = parent {
+            self.program(parent)?;
+        }
+
+        // Names of removed programs keep their id
+        let id = match self.name_ids.get(name) {
+            Some(&id) => id,
+            None => {
+                self.names.push(name.to_string());
+                self.name_ids.insert(name.to_string(), self.names.len() - 1);
+                self.programs.push(None);
+                self.names.len() - 1
+            }
+        };
+        self.programs[id] = Some(Program {
+            weight,
+            parent: None,
+            children: Vec::new(),
+            subtower_weight: weight as u64
+        });
+        if let Some(parent) = parent {
+            self.attach(id, parent);
+        }
+        return Ok(id);
+    }
+
+    pub fn remove_program(&mut self, id: ProgramId) -> Result<(), TowerEditError> {
+        if !self.program(id)?.children.is_empty() {
+            return Err(TowerEditError::HasChildren(id));
+        }
+        self.detach(id);
+        self.programs[id] = None;
+        return Ok(());
+    }
+
+    pub fn set_weight(&mut self, id: ProgramId, weight: u32) -> Result<(), TowerEditError> {
+        let old_weight = self.program(id)?.weight;
+        if weight == 0 {
+            return Err(TowerEditError::ZeroWeight);
+        }
+        self.program_mut(id).weight = weight;
+        self.add_to_subtower_weights(Some(id), weight as i64 - old_weight as i64);
+        return Ok(());
+    }
+
+    // Moves the program, along with everything it holds, onto new_parent or to the ground if None
+    pub fn reparent(&mut self, id: ProgramId, new_parent: Option<ProgramId>) -> Result<(), TowerEditError> {
+        self.program(id)?;
+        if let Some(new_parent) = new_parent {
+            self.program(new_parent)?;
+            let mut ancestor = Some(new_parent);
+            while let Some(ancestor_id) = ancestor {
+                if ancestor_id == id {
+                    return Err(TowerEditError::WouldCreateCycle { id, new_parent });
+                }
+                ancestor = self.program(ancestor_id)?.parent;
+            }
+        }
+
+        self.detach(id);
+        if let Some(new_parent) = new_parent {
+            self.attach(id, new_parent);
+        }
+        return Ok(());
+    }
+
+    fn program(&self, id: ProgramId) -> Result<&Program, TowerEditError> {
+        return self.programs.get(id).and_then(|program| program.as_ref()).ok_or(TowerEditError::UnknownProgram(id));
+    }
+
+    // Only for ids already checked with program()
+    fn program_mut(&mut self, id: ProgramId) -> &mut Program {
+        return self.programs[id].as_mut().unwrap();
+    }
+
+    fn attach(&mut self, id: ProgramId, parent: ProgramId) {
+        self.program_mut(id).parent = Some(parent);
+        self.program_mut(parent).children.push(id);
+        let subtower_weight = self.program_mut(id).subtower_weight;
+        self.add_to_subtower_weights(Some(parent), subtower_weight as i64);
+    }
+
+    fn detach(&mut self, id: ProgramId) {
+        if let Some(parent) = self.program_mut(id).parent.take() {
+            self.program_mut(parent).children.retain(|&child| child != id);
+            let subtower_weight = self.program_mut(id).subtower_weight;
+            self.add_to_subtower_weights(Some(parent), -(subtower_weight as i64));
+        }
+    }
+
+    // Updates the cached subtower weight of the program and every program holding it up
+    fn add_to_subtower_weights(&mut self, mut id: Option<ProgramId>, change: i64) {
+        while let Some(current) = id {
+            let program = self.program_mut(current);
+            program.subtower_weight = (program.subtower_weight as i64 + change) as u64;
+            id = program.parent;
+        }
+    }
+}
+
+#[cfg(test)]
+fn recomputed_subtower_weight(tower: &Tower, id: ProgramId) -> u64 {
+    let children_weight: u64 = tower.children(id).unwrap().iter().map(|&child| recomputed_subtower_weight(tower, child)).sum();
+    return tower.weight(id).unwrap() as u64 + children_weight;
+}
+
+#[test]
+fn test_tower_edits() {
+    let discs = super::parse_input(super::EXAMPLE_INPUT);
+    let mut tower = Tower::from_discs("tknk", &discs);
+    let tknk = tower.id("tknk").unwrap();
+    let ugml = tower.id("ugml").unwrap();
+    let padx = tower.id("padx").unwrap();
+    let pbga = tower.id("pbga").unwrap();
+    assert_eq!(Ok(778), tower.subtower_weight(tknk));
+    assert_eq!(Ok(251), tower.subtower_weight(ugml));
+
+    tower.set_weight(ugml, 60).unwrap();
+    assert_eq!(Ok(243), tower.subtower_weight(ugml));
+    assert_eq!(Ok(770), tower.subtower_weight(tknk));
+
+    let new_id = tower.add_program("new", 5, Some(pbga)).unwrap();
+    assert_eq!(Ok(248), tower.subtower_weight(padx));
+    assert_eq!(Err(TowerEditError::DuplicateName("new".to_string())), tower.add_program("new", 1, None));
+    assert_eq!(Err(TowerEditError::ZeroWeight), tower.add_program("other", 0, None));
+    assert_eq!(Err(TowerEditError::ZeroWeight), tower.set_weight(new_id, 0));
+    assert_eq!(Ok(5), tower.weight(new_id));
+    assert_eq!(Err(TowerEditError::HasChildren(pbga)), tower.remove_program(pbga));
+    assert_eq!(Err(TowerEditError::WouldCreateCycle { id: padx, new_parent: new_id }), tower.reparent(padx, Some(new_id)));
+
+    tower.reparent(padx, Some(ugml)).unwrap();
+    assert_eq!(Ok(Some(ugml)), tower.parent(padx));
+    assert_eq!(Ok(491), tower.subtower_weight(ugml));
+    assert_eq!(Ok(775), tower.subtower_weight(tknk));
+
+    tower.reparent(padx, None).unwrap();
+    assert_eq!(vec![tknk, padx], tower.roots());
+    assert_eq!(Ok(527), tower.subtower_weight(tknk));
+
+    tower.remove_program(new_id).unwrap();
+    assert_eq!(None, tower.id("new"));
+    assert_eq!(Err(TowerEditError::UnknownProgram(new_id)), tower.weight(new_id));
+    assert_eq!(Ok(243), tower.subtower_weight(padx));
+    assert_eq!(Ok(new_id), tower.add_program("new", 1, Some(tknk)));
+
+    for &id in tower.roots().iter() {
+        assert_eq!(recomputed_subtower_weight(&tower, id), tower.subtower_weight(id).unwrap());
+    }
+}
@@ -0,0 +1,190 @@
+// Compiles parsed register programs to a flat stack machine program with registers interned to
+// dense indices, so running it repeatedly doesn't re-parse anything or hash register names.
+// Conditions become conditional jumps, with && and || short-circuiting like the interpreter.
+
+use std::collections::HashMap;
+
+use instruction;
+use instruction::{ArithmeticOp, ComparisonOp, Condition, Expression, Instruction, Operation, ProgramError, Registers};
+use number::Number;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op<N> {
+    Push(N),
+    Load(usize),
+    Negate,
+    // Pops rhs then lhs and pushes the result
+    Arithmetic(ArithmeticOp),
+    // Pops rhs then lhs and jumps to the target when the comparison's result is jump_if
+    JumpIfCompare(ComparisonOp, bool, usize),
+    // Pops the amount and applies the operation to the register
+    Apply(Operation, usize),
+}
+
+pub struct CompiledProgram<N> {
+    register_names: Vec<String>,
+    register_indices: HashMap<String, usize>,
+    code: Vec<Op<N>>,
+    // Source line of each op, for errors
+    lines: Vec<usize>,
+}
+
+impl<N: Number> CompiledProgram<N> {
+    pub fn compile(program: &[(usize, Instruction<N>)]) -> CompiledProgram<N> {
+        let mut result = CompiledProgram {
+            register_names: Vec::new(),
+            register_indices: HashMap::new(),
+            code: Vec::new(),
+            lines: Vec::new(),
+        };
+        for &(line, ref instruction) in program.iter() {
+            // Every jump out of a false condition goes to the start of the next instruction
+            let mut skip_jumps = Vec::new();
+            if let Some(ref condition) = instruction.condition {
+                result.compile_branch(condition, false, line, &mut skip_jumps);
+            }
+            result.compile_expression(&instruction.amount, line);
+            let register = result.register_index(&instruction.register);
+            result.emit(Op::Apply(instruction.operation, register), line);
+            result.patch_jumps(&skip_jumps);
+        }
+        return result;
+    }
+
+    fn register_index(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.register_indices.get(name) {
+            return index;
+        }
+        self.register_names.push(name.to_string());
+        self.register_indices.insert(name.to_string(), self.register_names.len() - 1);
+        return self.register_names.len() - 1;
+    }
+
+    fn emit(&mut self, op: Op<N>, line: usize) {
+        self.code.push(op);
+        self.lines.push(line);
+    }
+
+    // Points the jumps at the next op to be emitted
+    fn patch_jumps(&mut self, jumps: &[usize]) {
+        let target = self.code.len();
+        for &index in jumps.iter() {
+            if let Op::JumpIfCompare(_, _, ref mut jump_target) = self.code[index] {
+                *jump_target = target;
+            }
+        }
+    }
+
+    fn compile_expression(&mut self, expression: &Expression<N>, line: usize) {
+        match *expression {
+            Expression::Literal(ref value) => self.emit(Op::Push(value.clone()), line),
+            Expression::Register(ref name) => {
+                let index = self.register_index(name);
+                self.emit(Op::Load(index), line);
+            }
+            Expression::Negate(ref inner) => {
+                self.compile_expression(inner, line);
+                self.emit(Op::Negate, line);
+            }
+            Expression::Binary(op, ref lhs, ref rhs) => {
+                self.compile_expression(lhs, line);
+                self.compile_expression(rhs, line);
+                self.emit(Op::Arithmetic(op), line);
+            }
+        }
+    }
+
+    // Emits code that falls through unless the condition's result is jump_if, in which case it
+    // jumps to a target that is filled in later. Those jumps are added to outer_jumps.
+    fn compile_branch(&mut self, condition: &Condition<N>, jump_if: bool, line: usize, outer_jumps: &mut Vec<usize>) {
+        match *condition {
+            Condition::Compare(op, ref lhs, ref rhs) => {
+                self.compile_expression(lhs, line);
+                self.compile_expression(rhs, line);
+                outer_jumps.push(self.code.len());
+                self.emit(Op::JumpIfCompare(op, jump_if, 0), line);
+            }
+            Condition::Not(ref inner) => self.compile_branch(inner, !jump_if, line, outer_jumps),
+            Condition::And(ref lhs, ref rhs) | Condition::Or(ref lhs, ref rhs) => {
+                let is_and = matches!(*condition, Condition::And(_, _));
+                if is_and != jump_if {
+                    // Either side alone decides: jump out if false for && or true for ||
+                    self.compile_branch(lhs, jump_if, line, outer_jumps);
+                    self.compile_branch(rhs, jump_if, line, outer_jumps);
+                } else {
+                    // The left side alone can only decide the other way, skipping the right side
+                    let mut local_jumps = Vec::new();
+                    self.compile_branch(lhs, !jump_if, line, &mut local_jumps);
+                    self.compile_branch(rhs, jump_if, line, outer_jumps);
+                    self.patch_jumps(&local_jumps);
+                }
+            }
+        }
+    }
+
+    // Runs the program starting from the given registers, anything not in there starting at 0.
+    // Returns the same as compute_register_values.
+    pub fn run(&self, initial_registers: &Registers<N>) -> Result<(Registers<N>, N), ProgramError> {
+        let mut registers = vec![N::zero(); self.register_names.len()];
+        let mut written = vec![false; self.register_names.len()];
+        for (index, name) in self.register_names.iter().enumerate() {
+            if let Some(value) = initial_registers.get(name) {
+                registers[index] = value.clone();
+                written[index] = true;
+            }
+        }
+
+        let max_value = self.execute(&mut registers, &mut written)?;
+
+        let mut result = initial_registers.clone();
+        for (index, name) in self.register_names.iter().enumerate() {
+            if written[index] {
+                result.insert(name.clone(), registers[index].clone());
+            }
+        }
+        return Ok((result, max_value));
+    }
+
+    fn execute(&self, registers: &mut [N], written: &mut [bool]) -> Result<N, ProgramError> {
+        let mut stack: Vec<N> = Vec::new();
+        let mut max_value = N::zero();
+        let mut pc = 0;
+        let error = |pc: usize, message: String| ProgramError { line: self.lines[pc], message };
+        while pc < self.code.len() {
+            match self.code[pc] {
+                Op::Push(ref value) => stack.push(value.clone()),
+                Op::Load(index) => stack.push(registers[index].clone()),
+                Op::Negate => {
+                    let value = stack.pop().unwrap().try_neg().map_err(|message| error(pc, message))?;
+                    stack.push(value);
+                }
+                Op::Arithmetic(op) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    let value = instruction::apply_arithmetic(op, &lhs, &rhs).map_err(|message| error(pc, message))?;
+                    stack.push(value);
+                }
+                Op::JumpIfCompare(op, jump_if, target) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    if instruction::compare(op, &lhs, &rhs) == jump_if {
+                        pc = target;
+                        continue;
+                    }
+                }
+                Op::Apply(operation, index) => {
+                    let amount = stack.pop().unwrap();
+                    let value = instruction::apply_operation(operation, &registers[index], amount)
+                        .map_err(|message| error(pc, message))?;
+                    if value > max_value {
+                        max_value = value.clone();
+                    }
+                    registers[index] = value;
+                    written[index] = true;
+                }
+            }
+            pc += 1;
+        }
+        return Ok(max_value);
+    }
+}
@@ -0,0 +1,330 @@
+// Interactive REPL and step debugger for register programs. Lines starting with ':' are
+// debugger commands, anything else is an instruction that runs straight away.
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+use instruction;
+use instruction::{Condition, Instruction, Registers};
+use number::Number;
+
+const HELP: &str = "\
+Commands:
+  INSTRUCTION          run an instruction right away, e.g. a inc 5 if b > 1
+  :load FILE           load a program and reset the registers
+  :list                show the program, > marks the next instruction
+  :step [COUNT]        run the next COUNT instructions (default 1)
+  :continue            run until a breakpoint or the end of the program
+  :until LINE          run until the instruction on LINE is next
+  :break CONDITION     stop once CONDITION becomes true, e.g. :break a >= 100
+  :breaks              list breakpoints
+  :delete INDEX        remove a breakpoint
+  :regs                show every register and the largest value held so far
+  :reset               clear the registers and go back to the start of the program
+  :help                show this message
+  :quit                exit";
+
+struct Breakpoint<N> {
+    source: String,
+    condition: Condition<N>,
+    // Breakpoints fire when their condition goes from false to true
+    was_true: bool,
+}
+
+struct ProgramLine<N> {
+    line: usize,
+    source: String,
+    instruction: Instruction<N>,
+}
+
+struct Debugger<N> {
+    program: Vec<ProgramLine<N>>,
+    // Index into program of the next instruction to run
+    next_index: usize,
+    registers: Registers<N>,
+    max_value: N,
+    breakpoints: Vec<Breakpoint<N>>,
+}
+
+// Why running stopped
+#[derive(Debug, PartialEq)]
+enum StopReason {
+    Stepped,
+    Breakpoint(usize),
+    ReachedLine(usize),
+    Finished,
+}
+
+impl<N: Number> Debugger<N> {
+    fn new() -> Debugger<N> {
+        return Debugger {
+            program: Vec::new(),
+            next_index: 0,
+            registers: Registers::new(),
+            max_value: N::zero(),
+            breakpoints: Vec::new(),
+        };
+    }
+
+    fn load(&mut self, input: &str) -> Result<(), String> {
+        let program = instruction::parse_program(input).map_err(|e| e.to_string())?;
+        let lines: Vec<&str> = input.lines().collect();
+        self.program = program.into_iter().map(|(line, instruction)| ProgramLine {
+            line,
+            source: lines[line - 1].trim().to_string(),
+            instruction,
+        }).collect();
+        self.reset();
+        return Ok(());
+    }
+
+    fn reset(&mut self) {
+        self.next_index = 0;
+        self.registers.clear();
+        self.max_value = N::zero();
+        self.update_breakpoints();
+    }
+
+    // Same bookkeeping as compute_register_values, with a line describing what happened
+    fn execute(&mut self, instruction: &Instruction<N>, source: &str) -> Result<String, String> {
+        let result = instruction::execute(instruction, &mut self.registers)?;
+        return Ok(match result {
+            Some(value) => {
+                let description = format!("{}  => taken, {} = {}", source, instruction.register, value);
+                if value > self.max_value {
+                    self.max_value = value;
+                }
+                description
+            }
+            None => format!("{}  => condition false, skipped", source),
+        });
+    }
+
+    // Records the breakpoint states, returning the index of the first one that just became true
+    fn update_breakpoints(&mut self) -> Option<usize> {
+        let mut result = None;
+        for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+            let is_true = instruction::evaluate_condition(&breakpoint.condition, &self.registers).unwrap_or(false);
+            if is_true && !breakpoint.was_true && result.is_none() {
+                result = Some(index);
+            }
+            breakpoint.was_true = is_true;
+        }
+        return result;
+    }
+
+    fn step(&mut self, output: &mut Vec<String>) -> Result<StopReason, String> {
+        if self.next_index >= self.program.len() {
+            return Ok(StopReason::Finished);
+        }
+        let (line, instruction) = (self.program[self.next_index].line, self.program[self.next_index].instruction.clone());
+        let source = format!("{:>4}: {}", line, self.program[self.next_index].source);
+        let description = self.execute(&instruction, &source).map_err(|message| format!("line {}: {}", line, message))?;
+        output.push(description);
+        self.next_index += 1;
+
+        if let Some(index) = self.update_breakpoints() {
+            return Ok(StopReason::Breakpoint(index));
+        }
+        if self.next_index >= self.program.len() {
+            return Ok(StopReason::Finished);
+        }
+        return Ok(StopReason::Stepped);
+    }
+
+    // Runs until a breakpoint, the end of the program, or the instruction on stop_line is next
+    fn run(&mut self, stop_line: Option<usize>, output: &mut Vec<String>) -> Result<StopReason, String> {
+        loop {
+            match self.step(output)? {
+                StopReason::Stepped => {}
+                reason => return Ok(reason),
+            }
+            if let Some(line) = stop_line {
+                if self.program[self.next_index].line == line {
+                    return Ok(StopReason::ReachedLine(line));
+                }
+            }
+        }
+    }
+
+    fn describe_stop(&self, reason: StopReason, output: &mut Vec<String>) {
+        match reason {
+            StopReason::Stepped => {}
+            StopReason::Breakpoint(index) => output.push(format!("Breakpoint {} hit: {}", index, self.breakpoints[index].source)),
+            StopReason::ReachedLine(line) => output.push(format!("Stopped before line {}", line)),
+            StopReason::Finished => output.push(format!("End of program, largest value held: {}", self.max_value)),
+        }
+    }
+
+    fn list(&self, output: &mut Vec<String>) {
+        for (index, program_line) in self.program.iter().enumerate() {
+            let marker = if index == self.next_index { ">" } else { " " };
+            output.push(format!("{} {:>4}: {}", marker, program_line.line, program_line.source));
+        }
+    }
+
+    fn show_registers(&self, output: &mut Vec<String>) {
+        let mut names: Vec<&String> = self.registers.keys().collect();
+        names.sort();
+        for name in names {
+            output.push(format!("{} = {}", name, self.registers[name]));
+        }
+        output.push(format!("largest value held: {}", self.max_value));
+    }
+
+    // Handles one line of REPL input, returning false once the user quits
+    fn handle_line(&mut self, line: &str, output: &mut Vec<String>) -> Result<bool, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(true);
+        }
+        if !line.starts_with(':') {
+            let instruction = instruction::parse_instruction(line)?;
+            let description = self.execute(&instruction, line)?;
+            output.push(description);
+            if let Some(index) = self.update_breakpoints() {
+                self.describe_stop(StopReason::Breakpoint(index), output);
+            }
+            return Ok(true);
+        }
+
+        let mut parts = line[1..].splitn(2, char::is_whitespace);
+        let command = parts.next().unwrap();
+        let argument = parts.next().map(|argument| argument.trim()).unwrap_or("");
+        match command {
+            "load" => {
+                let mut input = String::new();
+                File::open(argument).and_then(|mut f| f.read_to_string(&mut input)).map_err(|e| format!("Could not read {}: {}", argument, e))?;
+                self.load(&input)?;
+                output.push(format!("Loaded {} instructions", self.program.len()));
+            }
+            "list" => self.list(output),
+            "step" => {
+                let count = if argument.is_empty() { 1 } else { argument.parse::<usize>().map_err(|e| e.to_string())? };
+                for _ in 0..count {
+                    let reason = self.step(output)?;
+                    if reason != StopReason::Stepped {
+                        self.describe_stop(reason, output);
+                        break;
+                    }
+                }
+            }
+            "continue" => {
+                let reason = self.run(None, output)?;
+                self.describe_stop(reason, output);
+            }
+            "until" => {
+                let line = argument.parse::<usize>().map_err(|e| e.to_string())?;
+                if !self.program.iter().any(|program_line| program_line.line == line) {
+                    return Err(format!("There is no instruction on line {}", line));
+                }
+                let reason = self.run(Some(line), output)?;
+                self.describe_stop(reason, output);
+            }
+            "break" => {
+                // Reuse the instruction parser for the condition
+                let condition = match instruction::parse_instruction::<N>(&format!("_ set 0 if {}", argument))?.condition {
+                    Some(condition) => condition,
+                    None => return Err("Expected a condition".to_string()),
+                };
+                let was_true = instruction::evaluate_condition(&condition, &self.registers).unwrap_or(false);
+                self.breakpoints.push(Breakpoint { source: argument.to_string(), condition, was_true });
+                output.push(format!("Breakpoint {}: {}", self.breakpoints.len() - 1, argument));
+            }
+            "breaks" => {
+                for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+                    output.push(format!("{}: {}", index, breakpoint.source));
+                }
+            }
+            "delete" => {
+                let index = argument.parse::<usize>().map_err(|e| e.to_string())?;
+                if index >= self.breakpoints.len() {
+                    return Err(format!("There is no breakpoint {}", index));
+                }
+                self.breakpoints.remove(index);
+            }
+            "regs" => self.show_registers(output),
+            "reset" => self.reset(),
+            "help" => output.push(HELP.to_string()),
+            "quit" => return Ok(false),
+            _ => return Err(format!("Unknown command :{}, try :help", command)),
+        }
+        return Ok(true);
+    }
+}
+
+pub fn run_repl<N: Number, R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
+    let mut debugger: Debugger<N> = Debugger::new();
+    writeln!(output, "Register REPL, :help for commands")?;
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let mut lines = Vec::new();
+        let keep_going = match debugger.handle_line(&line?, &mut lines) {
+            Ok(keep_going) => keep_going,
+            Err(error) => {
+                lines.push(format!("error: {}", error));
+                true
+            }
+        };
+        for line in lines.iter() {
+            writeln!(output, "{}", line)?;
+        }
+        if !keep_going {
+            return Ok(());
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    return Ok(());
+}
+
+#[test]
+fn test_debugger() {
+    use number::Checked;
+
+    let mut debugger: Debugger<Checked> = Debugger::new();
+    debugger.load("a inc 5\nb inc 1 if a > 10\n\na inc 10\nb dec a if a > 10\nc inc 1").unwrap();
+    let mut output = Vec::new();
+
+    assert!(debugger.handle_line(":break a >= 15", &mut output).unwrap());
+    debugger.handle_line(":step 2", &mut output).unwrap();
+    assert_eq!(vec![
+        "Breakpoint 0: a >= 15",
+        "   1: a inc 5  => taken, a = 5",
+        "   2: b inc 1 if a > 10  => condition false, skipped",
+    ], output);
+
+    output.clear();
+    debugger.handle_line(":continue", &mut output).unwrap();
+    assert_eq!(vec!["   4: a inc 10  => taken, a = 15", "Breakpoint 0 hit: a >= 15"], output);
+
+    output.clear();
+    debugger.handle_line(":until 6", &mut output).unwrap();
+    debugger.handle_line(":list", &mut output).unwrap();
+    debugger.handle_line("c set b * 2 if b < 0", &mut output).unwrap();
+    debugger.handle_line(":regs", &mut output).unwrap();
+    assert_eq!(vec![
+        "   5: b dec a if a > 10  => taken, b = -15",
+        "Stopped before line 6",
+        "     1: a inc 5",
+        "     2: b inc 1 if a > 10",
+        "     4: a inc 10",
+        "     5: b dec a if a > 10",
+        ">    6: c inc 1",
+        "c set b * 2 if b < 0  => taken, c = -30",
+        "a = 15",
+        "b = -15",
+        "c = -30",
+        "largest value held: 15",
+    ], output);
+
+    output.clear();
+    debugger.handle_line(":continue", &mut output).unwrap();
+    assert_eq!(vec!["   6: c inc 1  => taken, c = -29", "End of program, largest value held: 15"], output);
+
+    assert_eq!(Err("Unknown command :frobnicate, try :help".to_string()), debugger.handle_line(":frobnicate", &mut output));
+    assert_eq!(Err("There is no instruction on line 3".to_string()), debugger.handle_line(":until 3", &mut output));
+    assert!(!debugger.handle_line(":quit", &mut output).unwrap());
+}
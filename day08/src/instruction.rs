@@ -0,0 +1,379 @@
+// Parsing and evaluation of register instructions of the form
+//
+//     REGISTER OPERATION EXPRESSION [if CONDITION]
+//
+// where OPERATION is one of inc, dec, set, mul, div or mod, EXPRESSION is arithmetic over
+// integer literals and registers using + - * / % and parentheses, and CONDITION compares
+// expressions with == != < <= > >=, combined with && || ! and parentheses.
+
+use std::fmt;
+use std::collections::HashMap;
+
+use number::Number;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    // Digits only, negative numbers are a negation of the literal
+    Number(String),
+    Symbol(&'static str),
+}
+
+// Longest first so "<=" isn't read as "<" followed by "="
+const SYMBOLS: [&str; 16] = [
+    "==", "!=", "<=", ">=", "&&", "||",
+    "<", ">", "!", "(", ")", "+", "-", "*", "/", "%",
+];
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let first = rest.chars().next().unwrap();
+        let length;
+        if first.is_ascii_alphabetic() || first == '_' {
+            length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..length].to_string()));
+        } else if first.is_ascii_digit() {
+            length = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            tokens.push(Token::Number(rest[..length].to_string()));
+        } else {
+            let symbol = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)).ok_or_else(|| format!("Unexpected character '{}'", first))?;
+            length = symbol.len();
+            tokens.push(Token::Symbol(symbol));
+        }
+        rest = rest[length..].trim_start();
+    }
+    return Ok(tokens);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression<N> {
+    Literal(N),
+    Register(String),
+    Negate(Box<Expression<N>>),
+    Binary(ArithmeticOp, Box<Expression<N>>, Box<Expression<N>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComparisonOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition<N> {
+    Compare(ComparisonOp, Expression<N>, Expression<N>),
+    Not(Box<Condition<N>>),
+    And(Box<Condition<N>>, Box<Condition<N>>),
+    Or(Box<Condition<N>>, Box<Condition<N>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Increment,
+    Decrement,
+    Set,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction<N> {
+    pub register: String,
+    pub operation: Operation,
+    pub amount: Expression<N>,
+    // Always executed when there is no condition
+    pub condition: Option<Condition<N>>,
+}
+
+// Parse or runtime error, with the 1-based line number of the instruction
+#[derive(Debug, PartialEq)]
+pub struct ProgramError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position);
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let result = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        return result;
+    }
+
+    fn next_is_symbol(&self, symbol: &'static str) -> bool {
+        return self.peek() == Some(&Token::Symbol(symbol));
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Symbol(s)) if s == symbol => Ok(()),
+            other => Err(format!("Expected '{}' but found {}", symbol, describe_token(other.as_ref()))),
+        }
+    }
+
+    fn parse_instruction<N: Number>(&mut self) -> Result<Instruction<N>, String> {
+        let register = match self.next() {
+            Some(Token::Identifier(name)) => name,
+            other => return Err(format!("Expected register but found {}", describe_token(other.as_ref()))),
+        };
+        let operation = match self.next() {
+            Some(Token::Identifier(ref name)) if name == "inc" => Operation::Increment,
+            Some(Token::Identifier(ref name)) if name == "dec" => Operation::Decrement,
+            Some(Token::Identifier(ref name)) if name == "set" => Operation::Set,
+            Some(Token::Identifier(ref name)) if name == "mul" => Operation::Multiply,
+            Some(Token::Identifier(ref name)) if name == "div" => Operation::Divide,
+            Some(Token::Identifier(ref name)) if name == "mod" => Operation::Remainder,
+            other => return Err(format!("Expected inc, dec, set, mul, div or mod but found {}", describe_token(other.as_ref()))),
+        };
+        let amount = self.parse_sum()?;
+        let condition = match self.next() {
+            None => None,
+            Some(Token::Identifier(ref name)) if name == "if" => Some(self.parse_or()?),
+            other => return Err(format!("Expected 'if' or end of line but found {}", describe_token(other.as_ref()))),
+        };
+        if let Some(token) = self.peek() {
+            return Err(format!("Unexpected {} after condition", describe_token(Some(token))));
+        }
+        return Ok(Instruction { register, operation, amount, condition });
+    }
+
+    fn parse_or<N: Number>(&mut self) -> Result<Condition<N>, String> {
+        let mut result = self.parse_and()?;
+        while self.next_is_symbol("||") {
+            self.next();
+            result = Condition::Or(Box::new(result), Box::new(self.parse_and()?));
+        }
+        return Ok(result);
+    }
+
+    fn parse_and<N: Number>(&mut self) -> Result<Condition<N>, String> {
+        let mut result = self.parse_not()?;
+        while self.next_is_symbol("&&") {
+            self.next();
+            result = Condition::And(Box::new(result), Box::new(self.parse_not()?));
+        }
+        return Ok(result);
+    }
+
+    fn parse_not<N: Number>(&mut self) -> Result<Condition<N>, String> {
+        if self.next_is_symbol("!") {
+            self.next();
+            return Ok(Condition::Not(Box::new(self.parse_not()?)));
+        }
+
+        // A '(' could start either a grouped condition or a grouped expression on the left of a
+        // comparison, so try the comparison first and fall back to the condition
+        let start = self.position;
+        match self.parse_comparison() {
+            Ok(comparison) => Ok(comparison),
+            Err(comparison_error) => {
+                if !self.tokens.get(start).is_some_and(|token| *token == Token::Symbol("(")) {
+                    return Err(comparison_error);
+                }
+                self.position = start + 1;
+                let result = self.parse_or()?;
+                self.expect_symbol(")")?;
+                Ok(result)
+            }
+        }
+    }
+
+    fn parse_comparison<N: Number>(&mut self) -> Result<Condition<N>, String> {
+        let lhs = self.parse_sum()?;
+        let op = match self.next() {
+            Some(Token::Symbol("==")) => ComparisonOp::Equal,
+            Some(Token::Symbol("!=")) => ComparisonOp::NotEqual,
+            Some(Token::Symbol("<")) => ComparisonOp::Less,
+            Some(Token::Symbol("<=")) => ComparisonOp::LessEqual,
+            Some(Token::Symbol(">")) => ComparisonOp::Greater,
+            Some(Token::Symbol(">=")) => ComparisonOp::GreaterEqual,
+            other => return Err(format!("Expected comparison but found {}", describe_token(other.as_ref()))),
+        };
+        let rhs = self.parse_sum()?;
+        return Ok(Condition::Compare(op, lhs, rhs));
+    }
+
+    fn parse_sum<N: Number>(&mut self) -> Result<Expression<N>, String> {
+        let mut result = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Symbol("+")) => ArithmeticOp::Add,
+                Some(&Token::Symbol("-")) => ArithmeticOp::Subtract,
+                _ => return Ok(result),
+            };
+            self.next();
+            result = Expression::Binary(op, Box::new(result), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product<N: Number>(&mut self) -> Result<Expression<N>, String> {
+        let mut result = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Symbol("*")) => ArithmeticOp::Multiply,
+                Some(&Token::Symbol("/")) => ArithmeticOp::Divide,
+                Some(&Token::Symbol("%")) => ArithmeticOp::Remainder,
+                _ => return Ok(result),
+            };
+            self.next();
+            result = Expression::Binary(op, Box::new(result), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary<N: Number>(&mut self) -> Result<Expression<N>, String> {
+        match self.next() {
+            // A literal takes its sign directly, as i64::MIN's digits on their own don't fit
+            Some(Token::Symbol("-")) => {
+                if let Some(Token::Number(digits)) = self.peek() {
+                    let literal = N::from_literal(digits, true)?;
+                    self.next();
+                    return Ok(Expression::Literal(literal));
+                }
+                Ok(Expression::Negate(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Symbol("(")) => {
+                let result = self.parse_sum()?;
+                self.expect_symbol(")")?;
+                Ok(result)
+            }
+            Some(Token::Number(digits)) => Ok(Expression::Literal(N::from_literal(&digits, false)?)),
+            Some(Token::Identifier(ref name)) if name == "if" => Err("Expected expression but found 'if'".to_string()),
+            Some(Token::Identifier(name)) => Ok(Expression::Register(name)),
+            other => Err(format!("Expected expression but found {}", describe_token(other.as_ref()))),
+        }
+    }
+}
+
+fn describe_token(token: Option<&Token>) -> String {
+    return match token {
+        None => "end of line".to_string(),
+        Some(Token::Identifier(name)) => format!("'{}'", name),
+        Some(Token::Number(digits)) => format!("'{}'", digits),
+        Some(&Token::Symbol(symbol)) => format!("'{}'", symbol),
+    };
+}
+
+pub fn parse_instruction<N: Number>(line: &str) -> Result<Instruction<N>, String> {
+    let mut parser = Parser { tokens: tokenize(line)?, position: 0 };
+    return parser.parse_instruction();
+}
+
+// Parses every non-empty line, line numbers in errors count from 1
+pub fn parse_program<N: Number>(input: &str) -> Result<Vec<(usize, Instruction<N>)>, ProgramError> {
+    let mut result = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let instruction = parse_instruction(line).map_err(|message| ProgramError { line: index + 1, message })?;
+        result.push((index + 1, instruction));
+    }
+    return Ok(result);
+}
+
+pub type Registers<N> = HashMap<String, N>;
+
+// Registers that were never written read as 0
+fn register_value<N: Number>(registers: &Registers<N>, name: &str) -> N {
+    return registers.get(name).cloned().unwrap_or_else(N::zero);
+}
+
+pub fn apply_arithmetic<N: Number>(op: ArithmeticOp, lhs: &N, rhs: &N) -> Result<N, String> {
+    return match op {
+        ArithmeticOp::Add => lhs.try_add(rhs),
+        ArithmeticOp::Subtract => lhs.try_sub(rhs),
+        ArithmeticOp::Multiply => lhs.try_mul(rhs),
+        ArithmeticOp::Divide => lhs.try_div(rhs),
+        ArithmeticOp::Remainder => lhs.try_rem(rhs),
+    };
+}
+
+pub fn compare<N: Number>(op: ComparisonOp, lhs: &N, rhs: &N) -> bool {
+    return match op {
+        ComparisonOp::Equal => lhs == rhs,
+        ComparisonOp::NotEqual => lhs != rhs,
+        ComparisonOp::Less => lhs < rhs,
+        ComparisonOp::LessEqual => lhs <= rhs,
+        ComparisonOp::Greater => lhs > rhs,
+        ComparisonOp::GreaterEqual => lhs >= rhs,
+    };
+}
+
+// The register's new value after applying the operation with the given amount
+pub fn apply_operation<N: Number>(operation: Operation, current: &N, amount: N) -> Result<N, String> {
+    return match operation {
+        Operation::Increment => current.try_add(&amount),
+        Operation::Decrement => current.try_sub(&amount),
+        Operation::Set => Ok(amount),
+        Operation::Multiply => current.try_mul(&amount),
+        Operation::Divide => current.try_div(&amount),
+        Operation::Remainder => current.try_rem(&amount),
+    };
+}
+
+pub fn evaluate_expression<N: Number>(expression: &Expression<N>, registers: &Registers<N>) -> Result<N, String> {
+    return match *expression {
+        Expression::Literal(ref value) => Ok(value.clone()),
+        Expression::Register(ref name) => Ok(register_value(registers, name)),
+        Expression::Negate(ref inner) => evaluate_expression(inner, registers)?.try_neg(),
+        Expression::Binary(op, ref lhs, ref rhs) => {
+            apply_arithmetic(op, &evaluate_expression(lhs, registers)?, &evaluate_expression(rhs, registers)?)
+        }
+    };
+}
+
+pub fn evaluate_condition<N: Number>(condition: &Condition<N>, registers: &Registers<N>) -> Result<bool, String> {
+    return match *condition {
+        Condition::Compare(op, ref lhs, ref rhs) => {
+            Ok(compare(op, &evaluate_expression(lhs, registers)?, &evaluate_expression(rhs, registers)?))
+        }
+        Condition::Not(ref inner) => Ok(!evaluate_condition(inner, registers)?),
+        Condition::And(ref lhs, ref rhs) => Ok(evaluate_condition(lhs, registers)? && evaluate_condition(rhs, registers)?),
+        Condition::Or(ref lhs, ref rhs) => Ok(evaluate_condition(lhs, registers)? || evaluate_condition(rhs, registers)?),
+    };
+}
+
+// Runs the instruction, returning the register's new value if the condition held
+pub fn execute<N: Number>(instruction: &Instruction<N>, registers: &mut Registers<N>) -> Result<Option<N>, String> {
+    if let Some(ref condition) = instruction.condition {
+        if !evaluate_condition(condition, registers)? {
+            return Ok(None);
+        }
+    }
+
+    let amount = evaluate_expression(&instruction.amount, registers)?;
+    let current = register_value(registers, &instruction.register);
+    let new_value = apply_operation(instruction.operation, &current, amount)?;
+    registers.insert(instruction.register.clone(), new_value.clone());
+    return Ok(Some(new_value));
+}
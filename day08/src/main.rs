@@ -1,7 +1,25 @@
+#![allow(clippy::needless_return)]
+#![cfg_attr(feature = "bench", feature(test))]
+
+#[cfg(feature = "bench")]
+extern crate test;
+
+use std::env;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+
+mod bytecode;
+mod debugger;
+mod instruction;
+mod number;
+
+use bytecode::CompiledProgram;
+use instruction::{ProgramError, Registers};
+use number::{Number, Checked, Saturating, Wrapping, BigInt};
+
+#[cfg(feature = "bench")]
+use test::Bencher;
 
 fn get_input() -> String {
     let mut f = File::open("input.txt")
@@ -13,62 +31,209 @@ fn get_input() -> String {
     return input_str;
 }
 
-fn compute_register_values<'a>(input: &'a str) -> (HashMap<&'a str, i32>, i32) {
-    let mut registers = HashMap::new();
-
-    let mut max_value = 0;
-    for line in input.lines() {
-        let mut word_iter = line.split_whitespace();
-        let register = word_iter.next().expect("Could not find register");
-        let inc_type = word_iter.next().expect("Could not get inc/dec");
-        let inc_amount_str = String::from(word_iter.next().expect("Could not get inc/dec value"));
-        let increment = match inc_type {
-            "inc" => inc_amount_str.parse::<i32>().expect("Failed to parse inc/dec amount"),
-            "dec" => -inc_amount_str.parse::<i32>().expect("Failed to parse inc/dec amount"),
-            _ => panic!("Unknown increment type {}", inc_type)
-        };
-        assert!(word_iter.next().expect("Missing if") == "if");
-        let cmp_register = word_iter.next().expect("Missing condition register");
-        let cmp_op = word_iter.next().expect("Missing comparison op");
-        let cmp_rhs = word_iter.next()
-            .expect("Missing comparison rhs")
-            .parse::<i32>()
-            .expect("Could not parse comparison rhs");
-
-        let cmp_register_value = *registers.get(cmp_register).unwrap_or(&0);
-
-        let cmp_success = match cmp_op {
-            "==" => cmp_register_value == cmp_rhs,
-            "!=" => cmp_register_value != cmp_rhs,
-            "<=" => cmp_register_value <= cmp_rhs,
-            "<"  => cmp_register_value <  cmp_rhs,
-            ">=" => cmp_register_value >= cmp_rhs,
-            ">"  => cmp_register_value >  cmp_rhs,
-            _    => panic!("Unkown comparison {}", cmp_op)
-        };
-
-        if cmp_success {
-            let value = match registers.entry(register) {
-                Entry::Vacant(v) => {
-                    *v.insert(increment)
-                }
-                Entry::Occupied(mut v) => {
-                    let new_value = *v.get() + increment;
-                    v.insert(new_value);
-                    new_value
-                }
-            };
+fn compute_register_values<N: Number>(input: &str) -> Result<(Registers<N>, N), ProgramError> {
+    let program = instruction::parse_program(input)?;
+    let mut registers = Registers::new();
+
+    let mut max_value = N::zero();
+    for (line, instruction) in program.iter() {
+        let result = instruction::execute(instruction, &mut registers)
+            .map_err(|message| ProgramError { line: *line, message })?;
+        if let Some(value) = result {
             max_value = max_value.max(value);
         }
     }
 
-    return (registers, max_value);
+    return Ok((registers, max_value));
 }
 
-fn main() {
-    let input = get_input();
-    let (registers, run_max) = compute_register_values(input.as_str());
-    let end_max = *registers.iter().max_by_key(|x| x.1).expect("No registers?").1;
+#[test]
+fn test_compute_register_values() {
+    let input = "b inc 5 if a > 1
+a inc 1 if b < 5
+c dec -10 if a >= 1
+c inc -20 if c == 10";
+    let (registers, run_max) = compute_register_values::<Checked>(input).unwrap();
+    assert_eq!(Some(&Checked(1)), registers.get("a"));
+    assert_eq!(Some(&Checked(-10)), registers.get("c"));
+    assert_eq!(None, registers.get("b"));
+    assert_eq!(Checked(10), run_max);
+
+    let input = "a set 7 * (2 + 1)
+b set a % 5 if a > 20 && !(a == 20 || a < 0)
+c set a / b
+a mod 4 if (a - 1) / 2 == 10
+d inc a + b - c if (a < 1 || b >= 1) && (c != 0)
+e mul 3 if e == 0
+f dec -(-2) if ((b == 1))";
+    let (registers, run_max) = compute_register_values::<Checked>(input).unwrap();
+    assert_eq!(Some(&Checked(1)), registers.get("a"));
+    assert_eq!(Some(&Checked(1)), registers.get("b"));
+    assert_eq!(Some(&Checked(21)), registers.get("c"));
+    assert_eq!(Some(&Checked(-19)), registers.get("d"));
+    assert_eq!(Some(&Checked(0)), registers.get("e"));
+    assert_eq!(Some(&Checked(-2)), registers.get("f"));
+    assert_eq!(Checked(21), run_max);
+
+    assert_eq!(Err(ProgramError { line: 2, message: "Division by zero".to_string() }),
+               compute_register_values::<Checked>("a inc 1\nb div a - 1"));
+    assert_eq!(Err(ProgramError { line: 1, message: "Expected comparison but found end of line".to_string() }),
+               compute_register_values::<Checked>("a inc 1 if b"));
+    assert_eq!(Err(ProgramError { line: 1, message: "Expected inc, dec, set, mul, div or mod but found 'add'".to_string() }),
+               compute_register_values::<Checked>("a add 1"));
+}
+
+#[test]
+fn test_numeric_modes() {
+    let input = "a set 9223372036854775807
+b inc 1
+a inc b
+a mul 2 if a < 0";
+    assert_eq!(Err(ProgramError { line: 3, message: "Overflow in addition".to_string() }),
+               compute_register_values::<Checked>(input));
+
+    let (registers, _) = compute_register_values::<Saturating>(input).unwrap();
+    assert_eq!(Some(&Saturating(i64::MAX)), registers.get("a"));
+
+    let (registers, run_max) = compute_register_values::<Wrapping>(input).unwrap();
+    assert_eq!(Some(&Wrapping(0)), registers.get("a"));
+    assert_eq!(Wrapping(i64::MAX), run_max);
+
+    let (registers, run_max) = compute_register_values::<BigInt>(input).unwrap();
+    assert_eq!("9223372036854775808", registers["a"].to_string());
+    assert_eq!("9223372036854775808", run_max.to_string());
+
+    let (registers, _) = compute_register_values::<BigInt>("a set 100000000000000000000 * -100000000000000000000").unwrap();
+    assert_eq!("-10000000000000000000000000000000000000000", registers["a"].to_string());
+    assert!(compute_register_values::<Checked>("a set 100000000000000000000").is_err());
+
+    // i64::MIN fits, even though its digits alone don't
+    let input = "a inc -9223372036854775808\nb set -100000000000000000000";
+    let (registers, _) = compute_register_values::<Checked>("a inc -9223372036854775808").unwrap();
+    assert_eq!(Some(&Checked(i64::MIN)), registers.get("a"));
+    assert_eq!(Err(ProgramError { line: 1, message: "Number -9223372036854775809 is too small".to_string() }),
+               compute_register_values::<Checked>("a inc -9223372036854775809"));
+    let (registers, _) = compute_register_values::<Saturating>(input).unwrap();
+    assert_eq!(Some(&Saturating(i64::MIN)), registers.get("a"));
+    assert_eq!(Some(&Saturating(i64::MIN)), registers.get("b"));
+}
+
+// Same as compute_register_values, but compiled to bytecode first
+fn compute_register_values_compiled<N: Number>(input: &str) -> Result<(Registers<N>, N), ProgramError> {
+    let program = CompiledProgram::compile(&instruction::parse_program(input)?);
+    return program.run(&Registers::new());
+}
+
+#[test]
+fn test_compiled_matches_interpreter() {
+    let inputs = [
+        "b inc 5 if a > 1\na inc 1 if b < 5\nc dec -10 if a >= 1\nc inc -20 if c == 10",
+        "a set 7 * (2 + 1)
+b set a % 5 if a > 20 && !(a == 20 || a < 0)
+c set a / b
+a mod 4 if (a - 1) / 2 == 10
+d inc a + b - c if (a < 1 || b >= 1) && (c != 0)
+e mul 3 if e == 0
+f dec -(-2) if ((b == 1))
+g inc 1 if !(a == 1 && b == 2) || c < 0
+h inc 1 if !(!(a > 0) || b > 0 && c > 100)
+i inc 1 if (a == 0 || b == 0 || c == 21) && !(d > 0 && e > 0)",
+        "a inc 1\nb div a - 1",
+        "a set 9223372036854775807\nb inc 1\na inc b",
+        "a inc 1 if 1 / b > 0 || a == 0",
+    ];
+    for input in inputs.iter() {
+        assert_eq!(compute_register_values::<Checked>(input), compute_register_values_compiled::<Checked>(input));
+        assert_eq!(compute_register_values::<Wrapping>(input), compute_register_values_compiled::<Wrapping>(input));
+    }
+    assert_eq!(compute_register_values::<Checked>(&get_input()), compute_register_values_compiled::<Checked>(&get_input()));
+
+    // Compile once and run from different starting registers
+    let program = CompiledProgram::compile(&instruction::parse_program::<Checked>("b inc a if a > 2\nc set a * b").unwrap());
+    for a in 0..5 {
+        let mut initial = Registers::new();
+        initial.insert("a".to_string(), Checked(a));
+        initial.insert("z".to_string(), Checked(100));
+        let (registers, run_max) = program.run(&initial).unwrap();
+        let b = if a > 2 { a } else { 0 };
+        assert_eq!(Some(&Checked(a)), registers.get("a"));
+        assert_eq!(Some(&Checked(100)), registers.get("z"));
+        assert_eq!(if a > 2 { Some(Checked(a)) } else { None }, registers.get("b").cloned());
+        assert_eq!(Some(&Checked(a * b)), registers.get("c"));
+        assert_eq!(Checked(a * b), run_max);
+    }
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn interpreter_bench(b: &mut Bencher) {
+    let program = instruction::parse_program::<Checked>(&get_input()).unwrap();
+    b.iter(|| {
+        let mut registers = Registers::new();
+        for (_, instruction) in program.iter() {
+            test::black_box(instruction::execute(instruction, &mut registers).unwrap());
+        }
+    });
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn compiled_bench(b: &mut Bencher) {
+    let program = CompiledProgram::compile(&instruction::parse_program::<Checked>(&get_input()).unwrap());
+    let initial = Registers::new();
+    b.iter(|| {
+        test::black_box(program.run(&initial).unwrap());
+    });
+}
+
+// Runs the program and prints both parts, or the error with its line number
+fn run<N: Number>(input: &str, compiled: bool) {
+    let result = if compiled {
+        compute_register_values_compiled::<N>(input)
+    } else {
+        compute_register_values::<N>(input)
+    };
+    let (registers, run_max) = match result {
+        Ok(result) => result,
+        Err(error) => panic!("{}", error),
+    };
+    let end_max = registers.values().max().expect("No registers?");
     println!("part1: {}", end_max);
     println!("part2: {}", run_max);
 }
+
+#[derive(Clone, Copy)]
+enum Runner {
+    Compiled,
+    Interpreter,
+    Repl,
+}
+
+fn start<N: Number>(runner: Runner) {
+    match runner {
+        Runner::Compiled => run::<N>(&get_input(), true),
+        Runner::Interpreter => run::<N>(&get_input(), false),
+        Runner::Repl => {
+            let stdin = io::stdin();
+            debugger::run_repl::<N, _, _>(stdin.lock(), io::stdout()).expect("Could not read or write the terminal");
+        }
+    }
+}
+
+fn main() {
+    // [MODE] to run input.txt as bytecode, interpret [MODE] to run it with the tree-walking
+    // interpreter, or repl [MODE] for an interactive session
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (runner, mode) = match args.first().map(String::as_str) {
+        Some("repl") => (Runner::Repl, args.get(1)),
+        Some("interpret") => (Runner::Interpreter, args.get(1)),
+        _ => (Runner::Compiled, args.first()),
+    };
+    match mode.map(String::as_str) {
+        None | Some("checked") => start::<Checked>(runner),
+        Some("saturating") => start::<Saturating>(runner),
+        Some("wrapping") => start::<Wrapping>(runner),
+        Some("big") => start::<BigInt>(runner),
+        Some(other) => panic!("Unknown numeric mode {}, expected checked, saturating, wrapping or big", other),
+    }
+}
@@ -0,0 +1,441 @@
+// Register value types, one per way of dealing with arithmetic that doesn't fit
+
+use std::cmp::Ordering;
+use std::fmt;
+
+pub trait Number: Clone + Ord + fmt::Display + fmt::Debug {
+    fn zero() -> Self;
+    // A decimal literal from the program text, negative if it came straight after a '-'. Taking
+    // the sign here rather than negating afterwards lets i64::MIN be written as a literal.
+    fn from_literal(digits: &str, negative: bool) -> Result<Self, String>;
+    fn try_add(&self, other: &Self) -> Result<Self, String>;
+    fn try_sub(&self, other: &Self) -> Result<Self, String>;
+    fn try_mul(&self, other: &Self) -> Result<Self, String>;
+    // Division and remainder round towards zero, like Rust's integer / and %
+    fn try_div(&self, other: &Self) -> Result<Self, String>;
+    fn try_rem(&self, other: &Self) -> Result<Self, String>;
+    fn try_neg(&self) -> Result<Self, String>;
+}
+
+fn overflow<T>(operation: &str) -> Result<T, String> {
+    return Err(format!("Overflow in {}", operation));
+}
+
+// The literal as an i64, None if it doesn't fit
+fn literal_to_i64(digits: &str, negative: bool) -> Option<i64> {
+    let magnitude = digits.parse::<u64>().ok()?;
+    if negative {
+        return 0i64.checked_sub_unsigned(magnitude);
+    }
+    return 0i64.checked_add_unsigned(magnitude);
+}
+
+fn check_divisor(divisor: i64, operation: &str) -> Result<(), String> {
+    if divisor == 0 {
+        return Err(format!("{} by zero", operation));
+    }
+    return Ok(());
+}
+
+// i64 that reports an error on overflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checked(pub i64);
+
+impl fmt::Display for Checked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Number for Checked {
+    fn zero() -> Self {
+        return Checked(0);
+    }
+
+    fn from_literal(digits: &str, negative: bool) -> Result<Self, String> {
+        return match literal_to_i64(digits, negative) {
+            Some(value) => Ok(Checked(value)),
+            None if negative => Err(format!("Number -{} is too small", digits)),
+            None => Err(format!("Number {} is too big", digits)),
+        };
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, String> {
+        return self.0.checked_add(other.0).map(Checked).map_or_else(|| overflow("addition"), Ok);
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, String> {
+        return self.0.checked_sub(other.0).map(Checked).map_or_else(|| overflow("subtraction"), Ok);
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, String> {
+        return self.0.checked_mul(other.0).map(Checked).map_or_else(|| overflow("multiplication"), Ok);
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, String> {
+        check_divisor(other.0, "Division")?;
+        return self.0.checked_div(other.0).map(Checked).map_or_else(|| overflow("division"), Ok);
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, String> {
+        check_divisor(other.0, "Remainder")?;
+        return self.0.checked_rem(other.0).map(Checked).map_or_else(|| overflow("remainder"), Ok);
+    }
+
+    fn try_neg(&self) -> Result<Self, String> {
+        return self.0.checked_neg().map(Checked).map_or_else(|| overflow("negation"), Ok);
+    }
+}
+
+// i64 that clamps to i64::MIN and i64::MAX
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Saturating(pub i64);
+
+impl fmt::Display for Saturating {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Number for Saturating {
+    fn zero() -> Self {
+        return Saturating(0);
+    }
+
+    fn from_literal(digits: &str, negative: bool) -> Result<Self, String> {
+        let limit = if negative { i64::MIN } else { i64::MAX };
+        return Ok(Saturating(literal_to_i64(digits, negative).unwrap_or(limit)));
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Saturating(self.0.saturating_add(other.0)));
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Saturating(self.0.saturating_sub(other.0)));
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Saturating(self.0.saturating_mul(other.0)));
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, String> {
+        check_divisor(other.0, "Division")?;
+        return Ok(Saturating(self.0.saturating_div(other.0)));
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, String> {
+        // The only overflowing case, i64::MIN % -1, is 0 anyway
+        check_divisor(other.0, "Remainder")?;
+        return Ok(Saturating(self.0.wrapping_rem(other.0)));
+    }
+
+    fn try_neg(&self) -> Result<Self, String> {
+        return Ok(Saturating(self.0.saturating_neg()));
+    }
+}
+
+// i64 that wraps around in two's complement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Wrapping(pub i64);
+
+impl fmt::Display for Wrapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Number for Wrapping {
+    fn zero() -> Self {
+        return Wrapping(0);
+    }
+
+    fn from_literal(digits: &str, negative: bool) -> Result<Self, String> {
+        // Keep the literal modulo 2^64
+        let mut value: u64 = 0;
+        for digit in digits.bytes() {
+            value = value.wrapping_mul(10).wrapping_add((digit - b'0') as u64);
+        }
+        let value = value as i64;
+        return Ok(Wrapping(if negative { value.wrapping_neg() } else { value }));
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Wrapping(self.0.wrapping_add(other.0)));
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Wrapping(self.0.wrapping_sub(other.0)));
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, String> {
+        return Ok(Wrapping(self.0.wrapping_mul(other.0)));
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, String> {
+        check_divisor(other.0, "Division")?;
+        return Ok(Wrapping(self.0.wrapping_div(other.0)));
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, String> {
+        check_divisor(other.0, "Remainder")?;
+        return Ok(Wrapping(self.0.wrapping_rem(other.0)));
+    }
+
+    fn try_neg(&self) -> Result<Self, String> {
+        return Ok(Wrapping(self.0.wrapping_neg()));
+    }
+}
+
+// Arbitrary precision integer stored as base 10^9 limbs, least significant first, with no
+// trailing zero limbs. Zero has no limbs and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+const LIMB_BASE: u64 = 1_000_000_000;
+const LIMB_DIGITS: usize = 9;
+
+fn trim_limbs(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x.cmp(y);
+        }
+    }
+    return Ordering::Equal;
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry: u64 = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push((sum % LIMB_BASE) as u32);
+        carry = sum / LIMB_BASE;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    return result;
+}
+
+// a must be at least b
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow: i64 = 0;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut difference = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += LIMB_BASE as i64;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+    trim_limbs(&mut result);
+    return result;
+}
+
+fn mul_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0u64; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry: u64 = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let product = result[i + j] + x as u64 * y as u64 + carry;
+            result[i + j] = product % LIMB_BASE;
+            carry = product / LIMB_BASE;
+        }
+        result[i + b.len()] += carry;
+    }
+    let mut result: Vec<u32> = result.into_iter().map(|limb| limb as u32).collect();
+    trim_limbs(&mut result);
+    return result;
+}
+
+// Long division one limb at a time, binary searching each quotient limb
+fn div_rem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for i in (0..a.len()).rev() {
+        remainder.insert(0, a[i]);
+        trim_limbs(&mut remainder);
+
+        let (mut low, mut high) = (0u64, LIMB_BASE - 1);
+        while low < high {
+            let middle = (low + high).div_ceil(2);
+            if compare_magnitudes(&mul_magnitudes(b, &[middle as u32]), &remainder) == Ordering::Greater {
+                high = middle - 1;
+            } else {
+                low = middle;
+            }
+        }
+        quotient[i] = low as u32;
+        remainder = sub_magnitudes(&remainder, &mul_magnitudes(b, &[low as u32]));
+    }
+    trim_limbs(&mut quotient);
+    return (quotient, remainder);
+}
+
+impl BigInt {
+    fn new(negative: bool, limbs: Vec<u32>) -> BigInt {
+        let negative = negative && !limbs.is_empty();
+        return BigInt { negative, limbs };
+    }
+
+    fn is_zero(&self) -> bool {
+        return self.limbs.is_empty();
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        return match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+            (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+        };
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", self.limbs.last().unwrap())?;
+        for limb in self.limbs.iter().rev().skip(1) {
+            write!(f, "{:01$}", limb, LIMB_DIGITS)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Number for BigInt {
+    fn zero() -> Self {
+        return BigInt::new(false, Vec::new());
+    }
+
+    fn from_literal(digits: &str, negative: bool) -> Result<Self, String> {
+        let mut limbs = Vec::new();
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(LIMB_DIGITS);
+            limbs.push(digits[start..end].parse::<u32>().map_err(|e| e.to_string())?);
+            end = start;
+        }
+        trim_limbs(&mut limbs);
+        return Ok(BigInt::new(negative, limbs));
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, String> {
+        if self.negative == other.negative {
+            return Ok(BigInt::new(self.negative, add_magnitudes(&self.limbs, &other.limbs)));
+        }
+        return Ok(match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_magnitudes(&other.limbs, &self.limbs)),
+            _ => BigInt::new(self.negative, sub_magnitudes(&self.limbs, &other.limbs)),
+        });
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, String> {
+        return self.try_add(&other.try_neg()?);
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, String> {
+        return Ok(BigInt::new(self.negative != other.negative, mul_magnitudes(&self.limbs, &other.limbs)));
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, String> {
+        if other.is_zero() {
+            return Err("Division by zero".to_string());
+        }
+        let (quotient, _) = div_rem_magnitudes(&self.limbs, &other.limbs);
+        return Ok(BigInt::new(self.negative != other.negative, quotient));
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, String> {
+        if other.is_zero() {
+            return Err("Remainder by zero".to_string());
+        }
+        let (_, remainder) = div_rem_magnitudes(&self.limbs, &other.limbs);
+        return Ok(BigInt::new(self.negative, remainder));
+    }
+
+    fn try_neg(&self) -> Result<Self, String> {
+        return Ok(BigInt::new(!self.negative, self.limbs.clone()));
+    }
+}
+
+#[test]
+fn test_big_int() {
+    let big = |digits: &str| {
+        match digits.strip_prefix('-') {
+            Some(digits) => BigInt::from_literal(digits, true).unwrap(),
+            None => BigInt::from_literal(digits, false).unwrap(),
+        }
+    };
+    let a = big("123456789012345678901234567890");
+    let b = big("-987654321098765432");
+    assert_eq!("123456789012345678901234567890", a.to_string());
+    assert_eq!("-987654321098765432", b.to_string());
+    assert_eq!(big("123456789011358024580135802458"), a.try_add(&b).unwrap());
+    assert_eq!(big("123456789013333333222333333322"), a.try_sub(&b).unwrap());
+    assert_eq!(big("-121932631137021795212620027521140070120989178480"), a.try_mul(&b).unwrap());
+    assert_eq!(big("-124999998860"), a.try_div(&b).unwrap());
+    assert_eq!(big("925925953827160370"), a.try_rem(&b).unwrap());
+    assert_eq!(big("-925925953827160370"), a.try_neg().unwrap().try_rem(&b).unwrap());
+    assert_eq!(big("0"), b.try_sub(&b).unwrap());
+    assert_eq!(BigInt::zero(), big("-0"));
+    assert!(b < BigInt::zero() && BigInt::zero() < a && big("-5") < big("-4"));
+    assert_eq!(Err("Division by zero".to_string()), a.try_div(&BigInt::zero()));
+
+    for &(x, y) in [(7i64, 2i64), (-7, 2), (7, -2), (-7, -2), (1, 1000000000000), (999999999999999999, 1000000000)].iter() {
+        let (bx, by) = (big(&x.to_string()), big(&y.to_string()));
+        assert_eq!((x / y).to_string(), bx.try_div(&by).unwrap().to_string());
+        assert_eq!((x % y).to_string(), bx.try_rem(&by).unwrap().to_string());
+        if let Some(product) = x.checked_mul(y) {
+            assert_eq!(product.to_string(), bx.try_mul(&by).unwrap().to_string());
+        }
+    }
+}
+
+#[test]
+fn test_from_literal() {
+    assert_eq!(Ok(Checked(i64::MIN)), Checked::from_literal("9223372036854775808", true));
+    assert_eq!(Ok(Checked(i64::MAX)), Checked::from_literal("9223372036854775807", false));
+    assert_eq!(Err("Number 9223372036854775808 is too big".to_string()), Checked::from_literal("9223372036854775808", false));
+    assert_eq!(Err("Number -9223372036854775809 is too small".to_string()), Checked::from_literal("9223372036854775809", true));
+    assert_eq!(Ok(Checked(0)), Checked::from_literal("0", true));
+
+    assert_eq!(Ok(Saturating(i64::MIN)), Saturating::from_literal("9223372036854775808", true));
+    assert_eq!(Ok(Saturating(i64::MIN)), Saturating::from_literal("100000000000000000000", true));
+    assert_eq!(Ok(Saturating(i64::MAX)), Saturating::from_literal("100000000000000000000", false));
+
+    assert_eq!(Ok(Wrapping(i64::MIN)), Wrapping::from_literal("9223372036854775808", true));
+    assert_eq!(Ok(Wrapping(-1)), Wrapping::from_literal("18446744073709551617", true));
+    assert_eq!("-9223372036854775808", BigInt::from_literal("9223372036854775808", true).unwrap().to_string());
+}
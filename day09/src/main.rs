@@ -1,91 +1,93 @@
+#![allow(clippy::needless_return)]
+
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 
-fn skip_garbage<T>(char_iter: &mut std::iter::Peekable<T>) -> u32
-where
-    T: Iterator<Item = char>,
-{
-    // Skip the opening '<'
-    if let Some(c) = char_iter.next() {
-        assert!(c == '<', "Garbage must start with '<', instead got {}", c);
-    } else {
-        panic!("Garbage has end of input")
-    }
+mod stream;
+mod tree;
 
-    let mut garbage_count = 0;
-    while let Some(c) = char_iter.next() {
-        if c == '>' {
-            break;
-        } else if c == '!' {
-            char_iter.next();
-        } else {
-            garbage_count += 1;
-        }
-    }
-    assert!(
-        char_iter.peek().is_some(),
-        "Unexpected end of input in garbage"
-    );
+use stream::{StreamError, StreamParser, StreamReport};
+use tree::StreamTree;
 
-    return garbage_count;
-}
+const CHUNK_SIZE: usize = 4096;
+
+fn get_input() -> String {
+    let mut f = File::open("input.txt").expect("Could not open file");
 
-fn count_groups<T>(char_iter: &mut std::iter::Peekable<T>, depth: u32) -> (u32, u32)
-where
-    T: Iterator<Item = char>,
-{
-    // Skip the opening '{'
-    assert!(char_iter.peek() == Some(&'{'));
-    char_iter.next();
+    let mut input_str = String::new();
+    f.read_to_string(&mut input_str)
+        .expect("Could not read file");
+    return input_str;
+}
 
-    let mut group_count = 0;
-    let mut garbage_count = 0;
+// Feeds the stream to the parser a chunk at a time, so it never has to be loaded all at once.
+// With progress, the running totals are printed after every chunk.
+fn parse_stream<R: Read>(mut input: R, progress: bool) -> StreamReport {
+    let mut parser = StreamParser::new();
+    let mut buffer = [0; CHUNK_SIZE];
     loop {
-        // Count subgroup or garbage
-        match char_iter.peek() {
-            Some(&c) => {
-                if c == '{' {
-                    let (subgroup_count, subgroup_garbage) = count_groups(char_iter, depth + 1);
-                    group_count += subgroup_count;
-                    garbage_count += subgroup_garbage;
-                } else if c == '<' {
-                    garbage_count += skip_garbage(char_iter);
-                }
-            }
-            None => {
-                panic!("Unexpected end of input");
-            }
+        let length = input.read(&mut buffer).expect("Could not read file");
+        if length == 0 {
+            return parser.finish();
         }
-
-        // Check if we are continuing this group with ',', or ending this group with '}'
-        match char_iter.next() {
-            Some(c) => {
-                if c == '}' {
-                    // End of group
-                    return (depth + group_count, garbage_count);
-                } else {
-                    assert!(c == ',', "expected \',\' or \'}}\'; got \'{}\'", c);
-                }
-            }
-            None => {
-                panic!("Unexpected end of input");
-            }
+        parser.feed(&buffer[..length]);
+        if progress {
+            let totals = parser.totals();
+            println!("{} bytes: score {}, {} groups, {} garbage, {} errors", parser.offset(), totals.score,
+                     totals.group_count, totals.garbage_count, parser.errors().len());
         }
     }
 }
 
-fn get_input() -> String {
-    let mut f = File::open("input.txt").expect("Could not open file");
+fn print_errors(errors: &[StreamError]) {
+    for error in errors.iter() {
+        eprintln!("{}", error);
+    }
+    if !errors.is_empty() {
+        eprintln!("The stream is malformed, the output only covers the parts that could be recovered");
+    }
+}
 
-    let mut input_str = String::new();
-    f.read_to_string(&mut input_str)
-        .expect("Could not read file");
-    return input_str;
+// Prints statistics from the syntax tree
+fn print_tree_stats(tree: &StreamTree) {
+    let root = match tree.root {
+        Some(ref root) => root,
+        None => return,
+    };
+    let deepest = root.deepest_group();
+    println!("Groups: {}", root.groups().len());
+    println!("Deepest group: depth {} at bytes {}..{}", deepest.score, deepest.span.start, deepest.span.end);
+    if let Some(garbage) = root.largest_garbage() {
+        println!("Largest garbage: {} characters at bytes {}..{}", garbage.count, garbage.span.start, garbage.span.end);
+    }
+    let canceled: usize = root.garbage().iter().map(|garbage| garbage.canceled.len()).sum();
+    println!("Canceled characters: {}", canceled);
 }
 
 fn main() {
-    let input = get_input();
-    let (part1, part2) = count_groups(&mut input.chars().peekable(), 1);
-    println!("Part 1: {}", part1);
-    println!("Part 2: {}", part2);
+    // No argument for both parts, progress to show the totals as the stream is read, or pretty,
+    // canonical, outline or stats to build the syntax tree and show it in that form
+    let mode = env::args().nth(1);
+    match mode.as_deref() {
+        None | Some("progress") => {
+            let f = File::open("input.txt").expect("Could not open file");
+            let report = parse_stream(f, mode.is_some());
+            print_errors(&report.errors);
+            println!("Part 1: {}", report.totals.score);
+            println!("Part 2: {}", report.totals.garbage_count);
+        }
+        Some("pretty") | Some("canonical") | Some("outline") | Some("stats") => {
+            let input = get_input();
+            let tree = StreamTree::parse(&input);
+            print_errors(&tree.errors);
+            match mode.as_deref() {
+                Some("pretty") => print!("{}", tree.pretty_print()),
+                Some("canonical") => println!("{}", tree.canonicalize()),
+                Some("outline") => print!("{}", tree.outline()),
+                _ => print_tree_stats(&tree),
+            }
+        }
+        Some(other) => panic!("Unknown mode {}, expected progress, pretty, canonical, outline or stats", other),
+    }
 }
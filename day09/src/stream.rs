@@ -0,0 +1,427 @@
+// Resumable parser for the group stream. It can be fed the stream in chunks of any size, split
+// anywhere, and never recurses, so deeply nested or huge streams never need to be loaded at once
+// or blow the stack.
+//
+// Malformed streams don't stop the parser. Each problem is recorded with its byte offset and the
+// bytes around it, then the parser recovers so the totals still cover the rest of the stream:
+// a missing ',' between items is assumed, anything else unexpected is skipped up to the next
+// ',', '}', '{' or '<', and whatever is still open when the stream ends is closed.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+// Bytes of context kept on each side of an error
+const CONTEXT_RADIUS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    // Before the outermost group
+    Start,
+    // Just after a '{', expecting a group, garbage or '}'
+    GroupStart,
+    // Just after a ',', expecting a group or garbage
+    ItemStart,
+    // After a group or garbage, expecting ',' or '}'
+    AfterItem,
+    Garbage,
+    // After a '!' in garbage, the next character is skipped
+    GarbageCancel,
+    // Recovering from an error, waiting for a byte that makes sense again
+    Skipping,
+    // After the outermost group, only whitespace may follow
+    Done,
+    // Non-whitespace followed the outermost group, the rest of the stream is ignored
+    Trailing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamErrorKind {
+    // The byte isn't allowed here, expected is a description of what was
+    UnexpectedByte { found: u8, expected: &'static str },
+    // The stream ended before the outermost group started
+    Empty,
+    // The stream ended inside garbage, start is the offset of its '<'
+    UnterminatedGarbage { start: usize },
+    // The stream ended with groups still open, innermost is the offset of the last one's '{'
+    UnclosedGroups { count: usize, innermost: usize },
+    TrailingData(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamError {
+    // Byte offset from the start of the stream, errors found at the end are at its length
+    pub offset: usize,
+    pub kind: StreamErrorKind,
+    // Bytes around the error, starting at context_start
+    pub context: Vec<u8>,
+    pub context_start: usize,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            StreamErrorKind::UnexpectedByte { found, expected } =>
+                write!(f, "byte {}: expected {} but found {:?}", self.offset, expected, found as char)?,
+            StreamErrorKind::Empty => write!(f, "byte {}: the stream ended before any group", self.offset)?,
+            StreamErrorKind::UnterminatedGarbage { start } =>
+                write!(f, "byte {}: garbage starting at byte {} is never closed", self.offset, start)?,
+            StreamErrorKind::UnclosedGroups { count, innermost } =>
+                write!(f, "byte {}: {} groups are never closed, the innermost starting at byte {}", self.offset, count, innermost)?,
+            StreamErrorKind::TrailingData(found) =>
+                write!(f, "byte {}: unexpected {:?} after the outermost group", self.offset, found as char)?,
+        }
+        // Show the context on the next line with a caret under the error
+        let context: String = self.context.iter().map(|&byte| {
+            if byte == b' ' || byte.is_ascii_graphic() { byte as char } else { '.' }
+        }).collect();
+        write!(f, "\n    {}\n    {}^", context, " ".repeat(self.offset - self.context_start))
+    }
+}
+
+// Told about the structure of the stream as it is parsed. Offsets are byte offsets from the start
+// of the stream and ends are exclusive. Groups and garbage still open at the end of the stream are
+// closed there, so every open is matched by a close.
+pub trait StreamVisitor {
+    fn open_group(&mut self, _offset: usize) {}
+    fn close_group(&mut self, _end: usize) {}
+    fn open_garbage(&mut self, _offset: usize) {}
+    // A character in garbage skipped because of the '!' before it
+    fn cancel(&mut self, _offset: usize) {}
+    // count is the number of characters in the garbage that weren't canceled
+    fn close_garbage(&mut self, _end: usize, _count: u64) {}
+}
+
+impl StreamVisitor for () {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamTotals {
+    pub score: u64,
+    pub group_count: u64,
+    pub garbage_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamReport {
+    pub totals: StreamTotals,
+    // Empty for a well formed stream, otherwise the totals are only partial
+    pub errors: Vec<StreamError>,
+}
+
+#[derive(Debug)]
+pub struct StreamParser {
+    state: State,
+    // Offsets of the '{' of every open group, outermost first
+    open_groups: Vec<usize>,
+    // Offset of the '<' of the current garbage, and its character count so far
+    garbage_start: usize,
+    garbage_count: u64,
+    offset: usize,
+    totals: StreamTotals,
+    errors: Vec<StreamError>,
+    // The last few bytes fed, for the context before an error
+    recent: VecDeque<u8>,
+    // Indices of errors still collecting context after the error
+    pending_context: Vec<usize>,
+}
+
+impl Default for StreamParser {
+    fn default() -> StreamParser {
+        return StreamParser {
+            state: State::Start,
+            open_groups: Vec::new(),
+            garbage_start: 0,
+            garbage_count: 0,
+            offset: 0,
+            totals: StreamTotals { score: 0, group_count: 0, garbage_count: 0 },
+            errors: Vec::new(),
+            recent: VecDeque::with_capacity(CONTEXT_RADIUS),
+            pending_context: Vec::new(),
+        };
+    }
+}
+
+impl StreamParser {
+    pub fn new() -> StreamParser {
+        return Default::default();
+    }
+
+    // Totals for everything fed so far, groups count as soon as they are opened
+    pub fn totals(&self) -> StreamTotals {
+        return self.totals;
+    }
+
+    // Errors found so far, the most recent ones may still be missing some context
+    pub fn errors(&self) -> &[StreamError] {
+        return &self.errors;
+    }
+
+    // Number of bytes fed so far
+    pub fn offset(&self) -> usize {
+        return self.offset;
+    }
+
+    // Parses the next chunk of the stream
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.feed_with(chunk, &mut ());
+    }
+
+    pub fn feed_with<V: StreamVisitor>(&mut self, chunk: &[u8], visitor: &mut V) {
+        for &byte in chunk.iter() {
+            self.state = self.next_state(byte, visitor);
+            self.add_context(byte);
+            if self.recent.len() == CONTEXT_RADIUS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(byte);
+            self.offset += 1;
+        }
+    }
+
+    // Call once the stream has ended to close anything still open and get the final report
+    pub fn finish(self) -> StreamReport {
+        return self.finish_with(&mut ());
+    }
+
+    pub fn finish_with<V: StreamVisitor>(mut self, visitor: &mut V) -> StreamReport {
+        match self.state {
+            State::Start => self.error(StreamErrorKind::Empty),
+            State::Garbage | State::GarbageCancel => {
+                let start = self.garbage_start;
+                self.error(StreamErrorKind::UnterminatedGarbage { start });
+                visitor.close_garbage(self.offset, self.garbage_count);
+            }
+            _ => {}
+        }
+        if let Some(&innermost) = self.open_groups.last() {
+            let count = self.open_groups.len();
+            self.error(StreamErrorKind::UnclosedGroups { count, innermost });
+            for _ in 0..count {
+                visitor.close_group(self.offset);
+            }
+        }
+        return StreamReport { totals: self.totals, errors: self.errors };
+    }
+
+    // Records an error at the current offset, which is the byte about to be parsed
+    fn error(&mut self, kind: StreamErrorKind) {
+        self.pending_context.push(self.errors.len());
+        self.errors.push(StreamError {
+            offset: self.offset,
+            kind,
+            context: self.recent.iter().cloned().collect(),
+            context_start: self.offset - self.recent.len(),
+        });
+    }
+
+    // Adds the byte to the context of recent errors, including any error at this byte
+    fn add_context(&mut self, byte: u8) {
+        let offset = self.offset;
+        let errors = &mut self.errors;
+        self.pending_context.retain(|&index| {
+            let error = &mut errors[index];
+            error.context.push(byte);
+            return offset < error.offset + CONTEXT_RADIUS;
+        });
+    }
+
+    fn unexpected(&mut self, found: u8, expected: &'static str) -> State {
+        self.error(StreamErrorKind::UnexpectedByte { found, expected });
+        return State::Skipping;
+    }
+
+    fn open_group<V: StreamVisitor>(&mut self, visitor: &mut V) -> State {
+        visitor.open_group(self.offset);
+        self.open_groups.push(self.offset);
+        self.totals.score += self.open_groups.len() as u64;
+        self.totals.group_count += 1;
+        return State::GroupStart;
+    }
+
+    fn close_group<V: StreamVisitor>(&mut self, visitor: &mut V) -> State {
+        visitor.close_group(self.offset + 1);
+        self.open_groups.pop();
+        return if self.open_groups.is_empty() { State::Done } else { State::AfterItem };
+    }
+
+    fn start_garbage<V: StreamVisitor>(&mut self, visitor: &mut V) -> State {
+        visitor.open_garbage(self.offset);
+        self.garbage_start = self.offset;
+        self.garbage_count = 0;
+        return State::Garbage;
+    }
+
+    fn next_state<V: StreamVisitor>(&mut self, byte: u8, visitor: &mut V) -> State {
+        return match (self.state, byte) {
+            (State::Start, b'{') => self.open_group(visitor),
+            (State::Start, _) if byte.is_ascii_whitespace() => State::Start,
+            (State::Start, _) => {
+                // Skip to the first '{', reporting only the first byte skipped
+                if self.errors.is_empty() {
+                    self.error(StreamErrorKind::UnexpectedByte { found: byte, expected: "'{'" });
+                }
+                State::Start
+            }
+            // Whitespace between items is ignored, so indented streams can be parsed
+            (State::GroupStart, _) | (State::ItemStart, _) | (State::AfterItem, _) if byte.is_ascii_whitespace() => self.state,
+            (State::GroupStart, b'}') => self.close_group(visitor),
+            (State::GroupStart, b'{') | (State::ItemStart, b'{') => self.open_group(visitor),
+            (State::GroupStart, b'<') | (State::ItemStart, b'<') => self.start_garbage(visitor),
+            (State::GroupStart, b',') | (State::ItemStart, b',') => {
+                // Empty item, carry on with the next one
+                self.error(StreamErrorKind::UnexpectedByte { found: byte, expected: "'{' or '<'" });
+                State::ItemStart
+            }
+            (State::ItemStart, b'}') => {
+                // Trailing ',' at the end of a group
+                self.error(StreamErrorKind::UnexpectedByte { found: byte, expected: "'{' or '<'" });
+                self.close_group(visitor)
+            }
+            (State::GroupStart, _) => self.unexpected(byte, "'{', '<' or '}'"),
+            (State::ItemStart, _) => self.unexpected(byte, "'{' or '<'"),
+            (State::AfterItem, b',') => State::ItemStart,
+            (State::AfterItem, b'}') => self.close_group(visitor),
+            (State::AfterItem, b'{') | (State::AfterItem, b'<') => {
+                // Missing ',' between items
+                self.error(StreamErrorKind::UnexpectedByte { found: byte, expected: "',' or '}'" });
+                if byte == b'{' { self.open_group(visitor) } else { self.start_garbage(visitor) }
+            }
+            (State::AfterItem, _) => self.unexpected(byte, "',' or '}'"),
+            (State::Skipping, b',') => State::ItemStart,
+            (State::Skipping, b'}') => self.close_group(visitor),
+            (State::Skipping, b'{') => self.open_group(visitor),
+            (State::Skipping, b'<') => self.start_garbage(visitor),
+            (State::Skipping, _) => State::Skipping,
+            (State::Garbage, b'>') => {
+                visitor.close_garbage(self.offset + 1, self.garbage_count);
+                State::AfterItem
+            }
+            (State::Garbage, b'!') => State::GarbageCancel,
+            (State::Garbage, _) => {
+                // Count characters rather than bytes, skipping UTF-8 continuation bytes
+                if byte & 0xc0 != 0x80 {
+                    self.totals.garbage_count += 1;
+                    self.garbage_count += 1;
+                }
+                State::Garbage
+            }
+            (State::GarbageCancel, _) => {
+                visitor.cancel(self.offset);
+                State::Garbage
+            }
+            (State::Done, _) if byte.is_ascii_whitespace() => State::Done,
+            (State::Done, _) => {
+                self.error(StreamErrorKind::TrailingData(byte));
+                State::Trailing
+            }
+            (State::Trailing, _) => State::Trailing,
+        };
+    }
+}
+
+#[cfg(test)]
+fn parse(input: &str) -> StreamReport {
+    let mut parser = StreamParser::new();
+    parser.feed(input.as_bytes());
+    return parser.finish();
+}
+
+#[cfg(test)]
+fn error_kinds(report: &StreamReport) -> Vec<(usize, StreamErrorKind)> {
+    return report.errors.iter().map(|error| (error.offset, error.kind)).collect();
+}
+
+#[test]
+fn test_stream_parser() {
+    let scores = [("{}", 1), ("{{{}}}", 6), ("{{},{}}", 5), ("{{{},{},{{}}}}", 16), ("{<a>,<a>,<a>,<a>}", 1),
+                  ("{{<ab>},{<ab>},{<ab>},{<ab>}}", 9), ("{{<!!>},{<!!>},{<!!>},{<!!>}}", 9),
+                  ("{{<a!>},{<a!>},{<a!>},{<ab>}}", 3)];
+    for &(input, score) in scores.iter() {
+        let report = parse(input);
+        assert_eq!(score, report.totals.score, "{}", input);
+        assert!(report.errors.is_empty(), "{}", input);
+    }
+    let garbage = [("<>", 0), ("<random characters>", 17), ("<<<<>", 3), ("<{!>}>", 2), ("<!!>", 0),
+                   ("<!!!>>", 0), ("<{o\"i!a,<{i<a>", 10), ("<é!é>", 1)];
+    for &(input, count) in garbage.iter() {
+        let report = parse(&format!("{{{}}}\n", input));
+        assert_eq!(count, report.totals.garbage_count, "{}", input);
+        assert!(report.errors.is_empty(), "{}", input);
+    }
+}
+
+#[test]
+fn test_stream_parser_errors() {
+    let unexpected = |found, expected| StreamErrorKind::UnexpectedByte { found, expected };
+
+    // Stray bytes are skipped up to the next ','
+    let report = parse("{{},xyz,{}}");
+    assert_eq!(vec![(4, unexpected(b'x', "'{' or '<'"))], error_kinds(&report));
+    assert_eq!(5, report.totals.score);
+
+    // Missing ',' between items
+    let report = parse("{{}{}<a>{}}");
+    assert_eq!(vec![(3, unexpected(b'{', "',' or '}'")), (5, unexpected(b'<', "',' or '}'")),
+                    (8, unexpected(b'{', "',' or '}'"))], error_kinds(&report));
+    assert_eq!(StreamTotals { score: 7, group_count: 4, garbage_count: 1 }, report.totals);
+
+    // Empty items and a trailing ','
+    let report = parse("{,{},,{},}");
+    assert_eq!(vec![(1, unexpected(b',', "'{' or '<'")), (5, unexpected(b',', "'{' or '<'")),
+                    (9, unexpected(b'}', "'{' or '<'"))], error_kinds(&report));
+    assert_eq!(5, report.totals.score);
+
+    // Truncated streams close everything still open
+    let report = parse("{{<a>},{{<ab!>c");
+    assert_eq!(vec![(15, StreamErrorKind::UnterminatedGarbage { start: 9 }),
+                    (15, StreamErrorKind::UnclosedGroups { count: 3, innermost: 8 })], error_kinds(&report));
+    assert_eq!(StreamTotals { score: 8, group_count: 4, garbage_count: 4 }, report.totals);
+
+    // Whitespace between items is fine
+    assert!(parse("{ {<a>} ,\n\t{ } }").errors.is_empty());
+
+    // Junk around the outermost group
+    let report = parse("xx{{}}}{}\n");
+    assert_eq!(vec![(0, unexpected(b'x', "'{'")), (6, StreamErrorKind::TrailingData(b'}'))], error_kinds(&report));
+    assert_eq!(3, report.totals.score);
+    assert_eq!(vec![(0, StreamErrorKind::Empty)], error_kinds(&parse("")));
+
+    // Context around each error, with a caret under the byte
+    let report = parse("{{<ab>},{<cd>},{<ef>}:{<gh>},{<ij>},{<kl>},{<mn>}}");
+    assert_eq!("byte 21: expected ',' or '}' but found ':'\n    <cd>},{<ef>}:{<gh>},{<ij>\n                ^",
+               report.errors[0].to_string());
+    assert_eq!("byte 5: 2 groups are never closed, the innermost starting at byte 1\n    {{<a>\n         ^",
+               parse("{{<a>").errors[0].to_string());
+}
+
+#[test]
+fn test_stream_parser_chunks() {
+    let inputs = ["{{<!!>},{<a{>},{{<'!!!>>}},{<é>}}", "{{},{x}{<a>}},,{{<!>},{{<ab"];
+    for input in inputs.iter() {
+        let expected = parse(input);
+        // Every way of splitting the stream in two, then one byte at a time
+        for split in 0..input.len() + 1 {
+            let mut parser = StreamParser::new();
+            parser.feed(&input.as_bytes()[..split]);
+            parser.feed(&input.as_bytes()[split..]);
+            assert_eq!(expected, parser.finish());
+        }
+        let mut parser = StreamParser::new();
+        let mut scores = Vec::new();
+        for byte in input.as_bytes().chunks(1) {
+            parser.feed(byte);
+            scores.push(parser.totals().score);
+        }
+        assert_eq!(expected, parser.finish());
+        assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    // Nesting far deeper than recursion could handle
+    let depth = 1000000;
+    let mut parser = StreamParser::new();
+    parser.feed("{".repeat(depth).as_bytes());
+    parser.feed("}".repeat(depth).as_bytes());
+    assert_eq!(StreamReport {
+        totals: StreamTotals { score: depth as u64 * (depth as u64 + 1) / 2, group_count: depth as u64, garbage_count: 0 },
+        errors: Vec::new(),
+    }, parser.finish());
+}
@@ -0,0 +1,379 @@
+// Syntax tree of a stream, built from the stream parser's events so malformed streams are
+// recovered the same way. Nodes only hold spans into the source, which the tree keeps hold of.
+
+use std::fmt::Write;
+
+use stream::{StreamError, StreamParser, StreamVisitor};
+
+// Byte range of the source, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub span: Span,
+    // Nesting depth, the outermost group scoring 1
+    pub score: u64,
+    pub children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Garbage {
+    pub span: Span,
+    // Offsets of the characters canceled by a '!'
+    pub canceled: Vec<usize>,
+    // Characters that weren't canceled
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Group(Group),
+    Garbage(Garbage),
+}
+
+pub struct StreamTree<'a> {
+    source: &'a str,
+    // None if the stream never started a group
+    pub root: Option<Group>,
+    pub errors: Vec<StreamError>,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    // Groups still open, innermost last
+    open_groups: Vec<Group>,
+    garbage: Option<Garbage>,
+    root: Option<Group>,
+}
+
+impl StreamVisitor for TreeBuilder {
+    fn open_group(&mut self, offset: usize) {
+        let score = self.open_groups.len() as u64 + 1;
+        self.open_groups.push(Group { span: Span { start: offset, end: offset }, score, children: Vec::new() });
+    }
+
+    fn close_group(&mut self, end: usize) {
+        let mut group = self.open_groups.pop().unwrap();
+        group.span.end = end;
+        match self.open_groups.last_mut() {
+            Some(parent) => parent.children.push(Node::Group(group)),
+            None => self.root = Some(group),
+        }
+    }
+
+    fn open_garbage(&mut self, offset: usize) {
+        self.garbage = Some(Garbage { span: Span { start: offset, end: offset }, canceled: Vec::new(), count: 0 });
+    }
+
+    fn cancel(&mut self, offset: usize) {
+        self.garbage.as_mut().unwrap().canceled.push(offset);
+    }
+
+    fn close_garbage(&mut self, end: usize, count: u64) {
+        let mut garbage = self.garbage.take().unwrap();
+        garbage.span.end = end;
+        garbage.count = count;
+        self.open_groups.last_mut().unwrap().children.push(Node::Garbage(garbage));
+    }
+}
+
+// The derived drop would recurse once per level of nesting, so the children are flattened out
+// instead and each group is dropped once it's empty
+impl Drop for Group {
+    fn drop(&mut self) {
+        let mut nodes: Vec<Node> = self.children.drain(..).collect();
+        while let Some(node) = nodes.pop() {
+            if let Node::Group(mut group) = node {
+                nodes.append(&mut group.children);
+            }
+        }
+    }
+}
+
+impl Group {
+    // Every group in the tree in order, starting with this one
+    pub fn groups(&self) -> Vec<&Group> {
+        let mut result = Vec::new();
+        let mut stack = vec![self];
+        while let Some(group) = stack.pop() {
+            result.push(group);
+            for child in group.children.iter().rev() {
+                if let Node::Group(ref child) = *child {
+                    stack.push(child);
+                }
+            }
+        }
+        return result;
+    }
+
+    // Every block of garbage in the tree in order
+    pub fn garbage(&self) -> Vec<&Garbage> {
+        let mut result: Vec<&Garbage> = self.groups().into_iter().flat_map(|group| {
+            group.children.iter().filter_map(|child| match *child {
+                Node::Garbage(ref garbage) => Some(garbage),
+                Node::Group(_) => None,
+            })
+        }).collect();
+        result.sort_by_key(|garbage| garbage.span.start);
+        return result;
+    }
+
+    // The first of the most deeply nested groups
+    pub fn deepest_group(&self) -> &Group {
+        return self.groups().into_iter().rev().max_by_key(|group| group.score).unwrap();
+    }
+
+    // The first of the blocks of garbage with the most characters
+    pub fn largest_garbage(&self) -> Option<&Garbage> {
+        return self.garbage().into_iter().rev().max_by_key(|garbage| garbage.count);
+    }
+}
+
+impl<'a> StreamTree<'a> {
+    pub fn parse(source: &'a str) -> StreamTree<'a> {
+        let mut parser = StreamParser::new();
+        let mut builder = TreeBuilder::default();
+        parser.feed_with(source.as_bytes(), &mut builder);
+        let report = parser.finish_with(&mut builder);
+        return StreamTree { source, root: builder.root, errors: report.errors };
+    }
+
+    pub fn text(&self, span: Span) -> &'a str {
+        return &self.source[span.start..span.end];
+    }
+
+    // The stream with one item per line, indented by depth. Anything the parser skipped is left
+    // out and anything left open is closed, so the result is always a well formed stream.
+    pub fn pretty_print(&self) -> String {
+        let mut result = String::new();
+        if let Some(ref root) = self.root {
+            self.pretty_print_group(root, &mut result);
+            result.push('\n');
+        }
+        return result;
+    }
+
+    fn pretty_print_group(&self, root: &Group, output: &mut String) {
+        if root.children.is_empty() {
+            output.push_str("{}");
+            return;
+        }
+        output.push_str("{\n");
+        // The children still to print of each open group, innermost last
+        let mut stack = vec![root.children.iter()];
+        while let Some(next) = stack.last_mut().map(|children| children.next()) {
+            let indent = "  ".repeat(stack.len());
+            match next {
+                Some(Node::Group(child)) if !child.children.is_empty() => {
+                    output.push_str(&indent);
+                    output.push_str("{\n");
+                    stack.push(child.children.iter());
+                    continue;
+                }
+                Some(Node::Group(_)) => {
+                    output.push_str(&indent);
+                    output.push_str("{}");
+                }
+                Some(Node::Garbage(garbage)) => {
+                    output.push_str(&indent);
+                    self.print_garbage(garbage, output);
+                }
+                None => {
+                    stack.pop();
+                    output.push_str(&indent[2..]);
+                    output.push('}');
+                }
+            }
+            // Whatever just finished is followed by a ',' if its group has more children
+            if let Some(children) = stack.last() {
+                output.push_str(if children.as_slice().is_empty() { "\n" } else { ",\n" });
+            }
+        }
+    }
+
+    fn print_garbage(&self, garbage: &Garbage, output: &mut String) {
+        let text = self.text(garbage.span);
+        output.push_str(text);
+        let last = garbage.span.end - 1;
+        let is_open = |c| last > garbage.span.start && text.ends_with(c) && !garbage.canceled.contains(&last);
+        if !is_open('>') {
+            // Never closed, so close it, first giving a '!' left at the end something to cancel
+            if is_open('!') {
+                output.push('!');
+            }
+            output.push('>');
+        }
+    }
+
+    // The groups alone, without garbage or whitespace
+    pub fn canonicalize(&self) -> String {
+        let mut result = String::new();
+        if let Some(ref root) = self.root {
+            canonicalize_group(root, &mut result);
+        }
+        return result;
+    }
+
+    // One line per group or block of garbage, with its span and score or character count
+    pub fn outline(&self) -> String {
+        let mut result = String::new();
+        if let Some(ref root) = self.root {
+            outline_group(root, &mut result);
+        }
+        return result;
+    }
+}
+
+fn outline_line(group: &Group, output: &mut String) {
+    let indent = "  ".repeat(group.score as usize - 1);
+    writeln!(output, "{}group {}..{} score {}", indent, group.span.start, group.span.end, group.score).unwrap();
+}
+
+// Walks the tree with a stack rather than recursing, as nesting can go as deep as the stream
+fn outline_group(root: &Group, output: &mut String) {
+    outline_line(root, output);
+    // The children still to outline of each group, innermost last, so the stack's length is the
+    // score of the group they're in
+    let mut stack = vec![root.children.iter()];
+    while let Some(next) = stack.last_mut().map(|children| children.next()) {
+        match next {
+            Some(Node::Group(child)) => {
+                outline_line(child, output);
+                stack.push(child.children.iter());
+            }
+            Some(Node::Garbage(garbage)) => {
+                writeln!(output, "{}garbage {}..{} {} characters, {} canceled", "  ".repeat(stack.len()),
+                         garbage.span.start, garbage.span.end, garbage.count, garbage.canceled.len()).unwrap();
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+fn canonicalize_group(root: &Group, output: &mut String) {
+    output.push('{');
+    // The children still to visit of each open group, with whether it has printed a group yet
+    let mut stack = vec![(root.children.iter(), false)];
+    while let Some((children, printed_any)) = stack.last_mut() {
+        match children.next() {
+            Some(Node::Group(child)) => {
+                if *printed_any {
+                    output.push(',');
+                }
+                *printed_any = true;
+                output.push('{');
+                stack.push((child.children.iter(), false));
+            }
+            Some(Node::Garbage(_)) => {}
+            None => {
+                output.push('}');
+                stack.pop();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_stream_tree() {
+    let input = "{{<a!>b>},{{<>},<x!!>},{}}";
+    let tree = StreamTree::parse(input);
+    assert!(tree.errors.is_empty());
+    let root = tree.root.as_ref().unwrap();
+    assert_eq!(Span { start: 0, end: input.len() }, root.span);
+    assert_eq!(vec![1, 2, 2, 3, 2], root.groups().iter().map(|group| group.score).collect::<Vec<_>>());
+    assert_eq!(vec!["{{<>},<x!!>}", "{<>}"], vec![tree.text(root.groups()[2].span), tree.text(root.deepest_group().span)]);
+
+    let garbage = root.garbage();
+    assert_eq!(vec!["<a!>b>", "<>", "<x!!>"], garbage.iter().map(|garbage| tree.text(garbage.span)).collect::<Vec<_>>());
+    assert_eq!(vec![5], garbage[0].canceled);
+    assert_eq!(vec![19], garbage[2].canceled);
+    assert_eq!(Some(garbage[0]), root.largest_garbage());
+
+    assert_eq!("{
+  {
+    <a!>b>
+  },
+  {
+    {
+      <>
+    },
+    <x!!>
+  },
+  {}
+}
+", tree.pretty_print());
+    assert_eq!("{{},{{}},{}}", tree.canonicalize());
+    assert_eq!("group 0..26 score 1
+  group 1..9 score 2
+    garbage 2..8 2 characters, 1 canceled
+  group 10..22 score 2
+    group 11..15 score 3
+      garbage 12..14 0 characters, 0 canceled
+    garbage 16..21 1 characters, 1 canceled
+  group 23..25 score 2
+", tree.outline());
+
+    // Both output forms are well formed and keep the score
+    for output in [tree.pretty_print(), tree.canonicalize()].iter() {
+        let reparsed = StreamTree::parse(output);
+        assert!(reparsed.errors.is_empty());
+        assert_eq!(root.groups().len(), reparsed.root.as_ref().unwrap().groups().len());
+    }
+}
+
+#[test]
+fn test_stream_tree_recovery() {
+    // Skipped bytes are dropped and anything left open is closed
+    let tree = StreamTree::parse("{{x},{<ab!");
+    assert_eq!(3, tree.errors.len());
+    assert_eq!("{\n  {},\n  {\n    <ab!!>\n  }\n}\n", tree.pretty_print());
+    assert_eq!("{{},{}}", tree.canonicalize());
+    assert!(StreamTree::parse(&tree.pretty_print()).errors.is_empty());
+
+    let tree = StreamTree::parse("{<a!!");
+    assert_eq!("{\n  <a!!>\n}\n", tree.pretty_print());
+
+    let tree = StreamTree::parse("  ");
+    assert_eq!(None, tree.root);
+    assert_eq!("", tree.pretty_print());
+}
+
+#[test]
+fn test_stream_tree_deep_nesting() {
+    // Nesting far deeper than recursion could handle, built, walked and dropped
+    let depth = 1000000;
+    let input = "{".repeat(depth) + &"}".repeat(depth);
+    let tree = StreamTree::parse(&input);
+    assert!(tree.errors.is_empty());
+    let root = tree.root.as_ref().unwrap();
+    assert_eq!(depth, root.groups().len());
+    assert_eq!(depth as u64, root.deepest_group().score);
+    assert!(root.garbage().is_empty());
+    assert_eq!(input, tree.canonicalize());
+    drop(tree);
+
+    // The indented forms grow with the square of the depth, so they get a shallower tree on a
+    // stack small enough that recursing would still overflow it
+    ::std::thread::Builder::new().stack_size(64 * 1024).spawn(|| {
+        let depth = 2000;
+        let input = "{".repeat(depth) + "<a>" + &"}".repeat(depth);
+        let tree = StreamTree::parse(&input);
+        assert!(tree.errors.is_empty());
+
+        let pretty = tree.pretty_print();
+        assert_eq!(2 * depth + 1, pretty.lines().count());
+        assert_eq!(Some("  ".repeat(depth) + "<a>"), pretty.lines().nth(depth).map(|line| line.to_string()));
+        assert_eq!("{".repeat(depth) + &"}".repeat(depth), StreamTree::parse(&pretty).canonicalize());
+
+        let outline = tree.outline();
+        assert_eq!(depth + 1, outline.lines().count());
+        assert_eq!(Some(format!("{}garbage {}..{} 1 characters, 0 canceled", "  ".repeat(depth), depth, depth + 3)),
+                   outline.lines().last().map(|line| line.to_string()));
+    }).unwrap().join().unwrap();
+}
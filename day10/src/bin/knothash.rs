@@ -0,0 +1,297 @@
+// Prints or checks knot hashes of files, with the same output format and options as sha256sum
+// so digest lists can be checked with either.
+
+#![allow(clippy::needless_return)]
+
+extern crate day10;
+
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::process;
+
+use day10::KnotParams;
+
+const USAGE: &str = "\
+Usage: knothash [OPTION]... [FILE]...
+Print or check knot hashes.
+With no FILE, or when FILE is -, read standard input.
+
+  -c, --check          read knot hashes from the FILEs and check them
+      --round LENGTHS  run a single round with the comma separated LENGTHS and print
+                       the product of the first two elements
+  -h, --help           display this help and exit
+
+The following options change the hash, use the same ones when checking:
+      --size N         number of elements in the ring (default 256)
+      --rounds N       number of rounds (default 64)
+      --suffix LENGTHS lengths added after the input's (default 17,31,73,47,23)
+      --block-size N   elements XORed together for each part of the digest (default 16)
+
+The following options are only useful when checking:
+      --quiet          don't print OK for each successfully verified file
+      --status         don't output anything, the exit code shows success
+      --strict         exit non-zero for improperly formatted checksum lines
+  -w, --warn           warn about improperly formatted checksum lines";
+
+#[derive(Default)]
+struct Options {
+    check: bool,
+    round: Option<String>,
+    quiet: bool,
+    status: bool,
+    strict: bool,
+    warn: bool,
+    params: KnotParams,
+    files: Vec<String>,
+}
+
+fn parse_number(option: &str, value: &str) -> Result<usize, String> {
+    return value.parse::<usize>().map_err(|e| format!("invalid {} '{}': {}", option, value, e));
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    let mut only_files = false;
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            options.files.push(arg.clone());
+            continue;
+        }
+        // Options with a value take it from the next argument or after an '='
+        let (option, inline_value) = match arg.find('=') {
+            Some(index) if arg.starts_with("--") => (&arg[..index], Some(arg[index + 1..].to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let takes_value = ["--round", "--size", "--rounds", "--suffix", "--block-size"].contains(&option);
+        let value = if !takes_value {
+            if inline_value.is_some() {
+                return Err(format!("option '{}' doesn't allow an argument", option));
+            }
+            String::new()
+        } else {
+            match inline_value {
+                Some(value) => value,
+                None => args.next().ok_or(format!("option '{}' requires an argument", option))?.clone(),
+            }
+        };
+        match option {
+            "--" => only_files = true,
+            "-c" | "--check" => options.check = true,
+            "--round" => options.round = Some(value),
+            "--size" => options.params.ring_size = parse_number(option, &value)?,
+            "--rounds" => options.params.rounds = parse_number(option, &value)?,
+            "--suffix" => options.params.suffix = day10::parse_lengths(&value)?,
+            "--block-size" => options.params.block_size = parse_number(option, &value)?,
+            "--quiet" => options.quiet = true,
+            "--status" => options.status = true,
+            "--strict" => options.strict = true,
+            "-w" | "--warn" => options.warn = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unrecognized option '{}'", arg)),
+        }
+    }
+    if options.files.is_empty() {
+        options.files.push("-".to_string());
+    }
+    return Ok(options);
+}
+
+fn read_file(name: &str) -> io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    if name == "-" {
+        io::stdin().read_to_end(&mut result)?;
+    } else {
+        File::open(name)?.read_to_end(&mut result)?;
+    }
+    return Ok(result);
+}
+
+// Like sha256sum, names with a backslash or newline are escaped and the line starts with '\'
+fn digest_line(digest: &str, name: &str) -> String {
+    if name.contains('\\') || name.contains('\n') {
+        return format!("\\{}  {}", digest, name.replace('\\', "\\\\").replace('\n', "\\n"));
+    }
+    return format!("{}  {}", digest, name);
+}
+
+// Splits a line of a digest list into the digest and file name, None if it is malformed
+fn parse_digest_line(line: &str, digest_length: usize) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    if line.len() < digest_length + 2 || !line.is_char_boundary(digest_length) {
+        return None;
+    }
+    let (digest, rest) = line.split_at(digest_length);
+    if !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    // Two spaces for text mode or a space and '*' for binary mode, both hash the same
+    let name = match rest.strip_prefix("  ").or_else(|| rest.strip_prefix(" *")) {
+        Some(name) if !name.is_empty() => name,
+        _ => return None,
+    };
+    if !escaped {
+        return Some((digest.to_ascii_lowercase(), name.to_string()));
+    }
+    let mut unescaped = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            _ => return None,
+        }
+    }
+    return Some((digest.to_ascii_lowercase(), unescaped));
+}
+
+fn hash_hex(contents: &[u8], params: &KnotParams) -> Result<String, String> {
+    return Ok(day10::dense_hash_to_hex(&day10::knot_hash_with(contents, params)?, params));
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    return format!("{} {}", count, if count == 1 { singular } else { plural });
+}
+
+// Prints the hash of each file, returning false if any couldn't be read
+fn hash_files(options: &Options) -> bool {
+    let mut ok = true;
+    for name in options.files.iter() {
+        match read_file(name).map_err(|e| e.to_string()).and_then(|contents| hash_hex(&contents, &options.params)) {
+            Ok(digest) => println!("{}", digest_line(&digest, name)),
+            Err(message) => {
+                eprintln!("knothash: {}: {}", name, message);
+                ok = false;
+            }
+        }
+    }
+    return ok;
+}
+
+// Checks every digest list, returning false if anything didn't match
+fn check_files(options: &Options) -> bool {
+    let mut ok = true;
+    let digest_length = options.params.ring_size / options.params.block_size * options.params.hex_digits();
+    for list_name in options.files.iter() {
+        let list = match read_file(list_name) {
+            Ok(list) => String::from_utf8_lossy(&list).into_owned(),
+            Err(e) => {
+                eprintln!("knothash: {}: {}", list_name, e);
+                ok = false;
+                continue;
+            }
+        };
+
+        let (mut improperly_formatted, mut mismatched, mut unreadable, mut properly_formatted) = (0, 0, 0, 0);
+        for (line_index, line) in list.lines().enumerate() {
+            let (expected, name) = match parse_digest_line(line, digest_length) {
+                Some(parsed) => parsed,
+                None => {
+                    improperly_formatted += 1;
+                    if options.warn {
+                        eprintln!("knothash: {}: {}: improperly formatted knot hash checksum line", list_name, line_index + 1);
+                    }
+                    continue;
+                }
+            };
+            properly_formatted += 1;
+            let result = match read_file(&name).map_err(|e| e.to_string()).and_then(|contents| hash_hex(&contents, &options.params)) {
+                Ok(ref digest) if *digest == expected => "OK",
+                Ok(_) => {
+                    mismatched += 1;
+                    "FAILED"
+                }
+                Err(message) => {
+                    eprintln!("knothash: {}: {}", name, message);
+                    unreadable += 1;
+                    "FAILED open or read"
+                }
+            };
+            if !(options.status || options.quiet && result == "OK") {
+                println!("{}: {}", name, result);
+            }
+        }
+
+        if properly_formatted == 0 {
+            eprintln!("knothash: {}: no properly formatted knot hash checksum lines found", list_name);
+            ok = false;
+            continue;
+        }
+        if !options.status {
+            if improperly_formatted > 0 {
+                eprintln!("knothash: WARNING: {} improperly formatted", plural(improperly_formatted, "line is", "lines are"));
+            }
+            if unreadable > 0 {
+                eprintln!("knothash: WARNING: {} not be read", plural(unreadable, "listed file could", "listed files could"));
+            }
+            if mismatched > 0 {
+                eprintln!("knothash: WARNING: {} NOT match", plural(mismatched, "computed checksum did", "computed checksums did"));
+            }
+        }
+        if mismatched > 0 || unreadable > 0 || (options.strict && improperly_formatted > 0) {
+            ok = false;
+        }
+    }
+    return ok;
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("knothash: {}\nTry 'knothash --help' for more information.", message);
+            process::exit(1);
+        }
+    };
+
+    let ok = if let Some(ref lengths) = options.round {
+        match day10::parse_lengths(lengths).and_then(|lengths| day10::single_round_product(&lengths, options.params.ring_size)) {
+            Ok(product) => {
+                println!("{}", product);
+                true
+            }
+            Err(message) => {
+                eprintln!("knothash: {}", message);
+                false
+            }
+        }
+    } else if let Err(message) = options.params.validate() {
+        eprintln!("knothash: {}", message);
+        false
+    } else if options.check {
+        check_files(&options)
+    } else {
+        hash_files(&options)
+    };
+    process::exit(if ok { 0 } else { 1 });
+}
+
+#[test]
+fn test_digest_lines() {
+    let digest = "a2582a3a0e66e6e86e3812dcb672a272";
+    for &name in ["input.txt", "with space", "back\\slash", "new\nline"].iter() {
+        assert_eq!(Some((digest.to_string(), name.to_string())), parse_digest_line(&digest_line(digest, name), 32));
+    }
+    assert_eq!("\\a2582a3a0e66e6e86e3812dcb672a272  back\\\\slash", digest_line(digest, "back\\slash"));
+    assert_eq!(Some((digest.to_string(), "bin".to_string())),
+               parse_digest_line("A2582A3A0E66E6E86E3812DCB672A272 *bin", 32));
+    assert_eq!(Some(("3a".to_string(), "x".to_string())), parse_digest_line("3a  x", 2));
+    for &line in ["", "a2582a3a0e66e6e86e3812dcb672a272", "a2582a3a0e66e6e86e3812dcb672a27  short",
+                  "g2582a3a0e66e6e86e3812dcb672a272  bad", "a2582a3a0e66e6e86e3812dcb672a272 one space",
+                  "\\a2582a3a0e66e6e86e3812dcb672a272  bad\\escape"].iter() {
+        assert_eq!(None, parse_digest_line(line, 32), "{}", line);
+    }
+}
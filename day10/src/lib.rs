@@ -0,0 +1,195 @@
+#![allow(clippy::needless_return)]
+
+// Everything that can be varied about the knot hash. The defaults are the puzzle's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnotParams {
+    pub ring_size: usize,
+    pub rounds: usize,
+    // Lengths added after the input's in every round
+    pub suffix: Vec<usize>,
+    // Number of elements XORed together for each element of the dense hash
+    pub block_size: usize,
+}
+
+impl Default for KnotParams {
+    fn default() -> KnotParams {
+        return KnotParams { ring_size: 256, rounds: 64, suffix: vec![17, 31, 73, 47, 23], block_size: 16 };
+    }
+}
+
+impl KnotParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ring_size == 0 {
+            return Err("The ring size must be at least 1".to_string());
+        }
+        if self.ring_size > u32::MAX as usize {
+            return Err(format!("The ring size must be at most {}", u32::MAX));
+        }
+        if self.block_size == 0 || !self.ring_size.is_multiple_of(self.block_size) {
+            return Err(format!("The block size must divide the ring size {}", self.ring_size));
+        }
+        if let Some(&length) = self.suffix.iter().find(|&&length| length > self.ring_size) {
+            return Err(format!("Suffix length {} is longer than the ring size {}", length, self.ring_size));
+        }
+        return Ok(());
+    }
+
+    // Hex digits needed for each element of the dense hash, 2 for the puzzle's ring
+    pub fn hex_digits(&self) -> usize {
+        let bits = (self.ring_size.next_power_of_two() - 1).count_ones() as usize;
+        return bits.div_ceil(4).max(1);
+    }
+}
+
+// A ring of elements along with the current position and skip size
+#[derive(Debug, Clone, PartialEq)]
+pub struct Knot {
+    elements: Vec<u32>,
+    position: usize,
+    skip: usize,
+}
+
+impl Knot {
+    pub fn new(ring_size: usize) -> Knot {
+        return Knot { elements: (0..ring_size as u32).collect(), position: 0, skip: 0 };
+    }
+
+    pub fn elements(&self) -> &[u32] {
+        return &self.elements;
+    }
+
+    // Reverses each length of elements in turn, failing if a length is longer than the ring
+    pub fn round<T>(&mut self, lengths: T) -> Result<(), String>
+    where
+        T: IntoIterator<Item = usize>
+    {
+        let ring_size = self.elements.len();
+        for length in lengths {
+            if length > ring_size {
+                return Err(format!("Length {} is longer than the ring size {}", length, ring_size));
+            }
+            for i in 0..(length / 2) {
+                let front = (self.position + i) % ring_size;
+                let back = (self.position + length - 1 - i) % ring_size;
+                self.elements.swap(front, back);
+            }
+            self.position = (self.position + length + self.skip) % ring_size;
+            // Only the skip modulo the ring size matters, keep it small so it can't overflow
+            self.skip = (self.skip + 1) % ring_size;
+        }
+        return Ok(());
+    }
+
+    // XORs each block of elements together
+    pub fn dense_hash(&self, block_size: usize) -> Vec<u32> {
+        return self.elements.chunks(block_size).map(|block| block.iter().fold(0, |acc, x| acc ^ x)).collect();
+    }
+}
+
+// Product of the first two elements after a single round with the given lengths
+pub fn single_round_product(lengths: &[usize], ring_size: usize) -> Result<u64, String> {
+    if ring_size < 2 {
+        return Err("The ring size must be at least 2".to_string());
+    }
+    let mut knot = Knot::new(ring_size);
+    knot.round(lengths.iter().cloned())?;
+    return Ok(knot.elements()[0] as u64 * knot.elements()[1] as u64);
+}
+
+// The dense hash of the input with the given parameters
+pub fn knot_hash_with(input: &[u8], params: &KnotParams) -> Result<Vec<u32>, String> {
+    params.validate()?;
+    let mut knot = Knot::new(params.ring_size);
+    let lengths: Vec<usize> = input.iter().map(|&b| b as usize).chain(params.suffix.iter().cloned()).collect();
+    for _ in 0..params.rounds {
+        knot.round(lengths.iter().cloned())?;
+    }
+    return Ok(knot.dense_hash(params.block_size));
+}
+
+pub fn knot_hash(input: &[u8]) -> [u8; 16]
+{
+    let dense_hash = knot_hash_with(input, &KnotParams::default()).expect("The default parameters are valid");
+    let mut result = [0; 16];
+    for (v, &element) in result.iter_mut().zip(dense_hash.iter()) {
+        *v = element as u8;
+    }
+    return result;
+}
+
+// Lowercase hex, as printed by sha256sum and friends
+pub fn to_hex(hash: &[u8]) -> String {
+    return hash.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+// Lowercase hex of a dense hash made with the parameters, the same as to_hex for the defaults
+pub fn dense_hash_to_hex(dense_hash: &[u32], params: &KnotParams) -> String {
+    let digits = params.hex_digits();
+    return dense_hash.iter().map(|element| format!("{:0width$x}", element, width = digits)).collect();
+}
+
+// Parses comma separated lengths such as "3,4,1,5", ignoring whitespace around each one
+pub fn parse_lengths(input: &str) -> Result<Vec<usize>, String> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    return input.trim().split(',').map(|length| {
+        length.trim().parse::<usize>().map_err(|e| format!("Invalid length '{}': {}", length.trim(), e))
+    }).collect();
+}
+
+#[test]
+fn test_knot_hash() {
+    let examples = [("", "a2582a3a0e66e6e86e3812dcb672a272"), ("AoC 2017", "33efeb34ea91902bb2f59c9920caa6cd"),
+                    ("1,2,3", "3efbe78a8d82f29979031a4aa0b16a9d"), ("1,2,4", "63960835bcdc130f0b66d7ff4f6a5a8e")];
+    for &(input, hash) in examples.iter() {
+        assert_eq!(hash, to_hex(&knot_hash(input.as_bytes())));
+        let params = KnotParams::default();
+        assert_eq!(hash, dense_hash_to_hex(&knot_hash_with(input.as_bytes(), &params).unwrap(), &params));
+    }
+}
+
+#[test]
+fn test_single_round_product() {
+    assert_eq!(Ok(vec![3, 4, 1, 5]), parse_lengths(" 3, 4,1,5\n"));
+    assert!(parse_lengths("3,x").is_err());
+    let lengths = parse_lengths("14,58,0,116,179,16,1,104,2,254,167,86,255,55,122,244").unwrap();
+    assert_eq!(Ok(1935), single_round_product(&lengths, 256));
+    assert_eq!(Ok(255 * 254), single_round_product(&[256], 256));
+    assert!(single_round_product(&[257], 256).is_err());
+}
+
+#[test]
+fn test_ring_of_5() {
+    // The puzzle's example
+    let mut knot = Knot::new(5);
+    let steps = [(3, [2, 1, 0, 3, 4], 3, 1), (4, [4, 3, 0, 1, 2], 3, 2), (1, [4, 3, 0, 1, 2], 1, 3), (5, [3, 4, 2, 1, 0], 4, 4)];
+    for &(length, elements, position, skip) in steps.iter() {
+        knot.round(vec![length]).unwrap();
+        assert_eq!(&elements, knot.elements());
+        assert_eq!((position, skip), (knot.position, knot.skip));
+    }
+    assert_eq!(Ok(12), single_round_product(&[3, 4, 1, 5], 5));
+    assert!(Knot::new(5).round(vec![6]).is_err());
+}
+
+#[test]
+fn test_knot_params() {
+    let params = KnotParams { ring_size: 10, rounds: 3, suffix: vec![7, 10], block_size: 5 };
+    assert_eq!(Ok(()), params.validate());
+    assert_eq!(1, params.hex_digits());
+    assert_eq!(2, knot_hash_with(b"\x03\x01", &params).unwrap().len());
+    assert_eq!(3, KnotParams { ring_size: 257, ..KnotParams::default() }.hex_digits());
+    assert_eq!(2, KnotParams { ring_size: 17, ..KnotParams::default() }.hex_digits());
+
+    // Input bytes longer than the ring are invalid lengths
+    assert!(knot_hash_with(b"\x0b", &params).is_err());
+    assert!(KnotParams { block_size: 3, ..params.clone() }.validate().is_err());
+    assert!(KnotParams { suffix: vec![11], ..params.clone() }.validate().is_err());
+    assert!(KnotParams { ring_size: 0, ..params.clone() }.validate().is_err());
+
+    // A single round without a suffix or dense hash is the same as part 1's round
+    let lengths = [3u8, 4, 1, 5];
+    let params = KnotParams { ring_size: 5, rounds: 1, suffix: Vec::new(), block_size: 1 };
+    assert_eq!(vec![3, 4, 2, 1, 0], knot_hash_with(&lengths, &params).unwrap());
+}
@@ -0,0 +1,154 @@
+// Parses comma separated hex directions such as "n,ne,SW" a token at a time from any reader, so
+// the whole walk never has to be in memory.
+
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    N,
+    NE,
+    SE,
+    S,
+    SW,
+    NW,
+}
+
+pub const ALL_DIRECTIONS: [Direction; 6] =
+    [Direction::N, Direction::NE, Direction::SE, Direction::S, Direction::SW, Direction::NW];
+
+impl Direction {
+    // Case insensitive, without any surrounding whitespace
+    pub fn parse(token: &str) -> Option<Direction> {
+        return ALL_DIRECTIONS.iter().cloned().find(|direction| direction.name().eq_ignore_ascii_case(token));
+    }
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            Direction::N => "n",
+            Direction::NE => "ne",
+            Direction::SE => "se",
+            Direction::S => "s",
+            Direction::SW => "sw",
+            Direction::NW => "nw",
+        };
+    }
+
+    // The step along the two diagonal axes described in main.rs
+    pub fn step(self) -> (i32, i32) {
+        return match self {
+            Direction::N => (1, 1),
+            Direction::NE => (0, 1),
+            Direction::SE => (-1, 0),
+            Direction::S => (-1, -1),
+            Direction::SW => (0, -1),
+            Direction::NW => (1, 0),
+        };
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug)]
+pub enum DirectionError {
+    // index counts the comma separated tokens from 0
+    Unknown { index: usize, token: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for DirectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DirectionError::Unknown { index, ref token } if token.is_empty() =>
+                write!(f, "Missing direction at index {}", index),
+            DirectionError::Unknown { index, ref token } =>
+                write!(f, "Unknown direction '{}' at index {}", token, index),
+            DirectionError::Io(ref error) => write!(f, "Could not read directions: {}", error),
+        }
+    }
+}
+
+// Iterates over the directions in a reader. Whitespace around each direction is ignored, as is
+// a trailing comma. Iteration stops after the first error.
+pub struct DirectionReader<R> {
+    reader: R,
+    index: usize,
+    token: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> DirectionReader<R> {
+    pub fn new(reader: R) -> DirectionReader<R> {
+        return DirectionReader { reader, index: 0, token: Vec::new(), done: false };
+    }
+}
+
+impl<R: BufRead> Iterator for DirectionReader<R> {
+    type Item = Result<Direction, DirectionError>;
+
+    fn next(&mut self) -> Option<Result<Direction, DirectionError>> {
+        if self.done {
+            return None;
+        }
+        self.token.clear();
+        if let Err(error) = self.reader.read_until(b',', &mut self.token) {
+            self.done = true;
+            return Some(Err(DirectionError::Io(error)));
+        }
+        let ends_with_comma = self.token.last() == Some(&b',');
+        if !ends_with_comma {
+            self.done = true;
+        }
+        let token = String::from_utf8_lossy(&self.token);
+        let token = token.trim_end_matches(',').trim();
+        if token.is_empty() && !ends_with_comma {
+            // Nothing but whitespace after the last comma, or an empty input
+            return None;
+        }
+
+        let index = self.index;
+        self.index += 1;
+        return Some(match Direction::parse(token) {
+            Some(direction) => Ok(direction),
+            None => {
+                self.done = true;
+                Err(DirectionError::Unknown { index, token: token.to_string() })
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+fn parse_all(input: &str) -> Result<Vec<Direction>, String> {
+    return DirectionReader::new(input.as_bytes()).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string());
+}
+
+#[test]
+fn test_direction_reader() {
+    use self::Direction::*;
+
+    assert_eq!(Ok(vec![N, NE, SE, S, SW, NW]), parse_all("n,ne,se,s,sw,nw"));
+    assert_eq!(Ok(vec![N, NE, S]), parse_all("N, nE ,\tS\n"));
+    assert_eq!(Ok(vec![N]), parse_all("n\r\n"));
+    assert_eq!(Ok(vec![N, S]), parse_all("n,s,\n"));
+    assert_eq!(Ok(vec![]), parse_all(""));
+    assert_eq!(Ok(vec![]), parse_all(" \n"));
+    assert_eq!(Err("Unknown direction 'north' at index 2".to_string()), parse_all("n,s,north,n"));
+    assert_eq!(Err("Missing direction at index 1".to_string()), parse_all("n,,s"));
+    assert_eq!(Err("Missing direction at index 0".to_string()), parse_all(","));
+
+    // Stops after an error
+    let mut reader = DirectionReader::new("x,n".as_bytes());
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+
+    // Directions split across the reader's buffer
+    let input = "ne,".repeat(10000);
+    let reader = io::BufReader::with_capacity(7, input.as_bytes());
+    assert_eq!(10000, DirectionReader::new(reader).map(|direction| direction.unwrap()).filter(|&d| d == NE).count());
+}
@@ -0,0 +1,230 @@
+// Cube coordinates for the hex grid. Every hex has q + r + s = 0, which makes distances, rings
+// and lines simple to compute. Going north adds 1 to s and takes 1 from r, going north east adds
+// 1 to q and takes 1 from r, and going south east adds 1 to q and takes 1 from s. The opposite
+// directions do the reverse.
+
+use std::cmp;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+use direction::{Direction, ALL_DIRECTIONS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cube {
+    pub q: i32,
+    pub r: i32,
+    pub s: i32,
+}
+
+pub const ORIGIN: Cube = Cube { q: 0, r: 0, s: 0 };
+
+impl Cube {
+    pub fn new(q: i32, r: i32) -> Cube {
+        return Cube { q, r, s: -q - r };
+    }
+
+    // The offset of one step in the direction
+    pub fn offset(direction: Direction) -> Cube {
+        return match direction {
+            Direction::N => Cube::new(0, -1),
+            Direction::NE => Cube::new(1, -1),
+            Direction::SE => Cube::new(1, 0),
+            Direction::S => Cube::new(0, 1),
+            Direction::SW => Cube::new(-1, 1),
+            Direction::NW => Cube::new(-1, 0),
+        };
+    }
+
+    // From the two diagonal axes used in main.rs. The first axis is s and the second is -r.
+    pub fn from_axes(axes: (i32, i32)) -> Cube {
+        return Cube { q: axes.1 - axes.0, r: -axes.1, s: axes.0 };
+    }
+
+    pub fn to_axes(self) -> (i32, i32) {
+        return (self.s, -self.r);
+    }
+
+    pub fn length(self) -> i32 {
+        return (self.q.abs() + self.r.abs() + self.s.abs()) / 2;
+    }
+
+    pub fn distance(self, other: Cube) -> i32 {
+        return (self - other).length();
+    }
+
+    pub fn neighbor(self, direction: Direction) -> Cube {
+        return self + Cube::offset(direction);
+    }
+
+    // Clockwise from the north
+    pub fn neighbors(self) -> impl Iterator<Item = Cube> {
+        return ALL_DIRECTIONS.iter().map(move |&direction| self.neighbor(direction));
+    }
+
+    // Every hex exactly radius steps away, clockwise from the northernmost
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = Cube> {
+        let radius = radius as i32;
+        let centre = std::iter::once(self).filter(move |_| radius == 0);
+        // Side i runs from the corner in direction i towards the corner in direction i + 1
+        let sides = (0..6).flat_map(move |side| {
+            let corner = self + Cube::offset(ALL_DIRECTIONS[side]) * radius;
+            let along = Cube::offset(ALL_DIRECTIONS[(side + 2) % 6]);
+            (0..radius).map(move |step| corner + along * step)
+        });
+        return centre.chain(sides);
+    }
+
+    // The hexes on the straight line to other, including both ends. Each hex is adjacent to the
+    // one before it.
+    pub fn line(self, other: Cube) -> impl Iterator<Item = Cube> {
+        let steps = self.distance(other);
+        // Nudge the line off the edges between hexes so ties always round the same way
+        let start = (self.q as f64 + 1e-6, self.r as f64 + 2e-6, self.s as f64 - 3e-6);
+        return (0..=steps).map(move |step| {
+            let t = if steps == 0 { 0.0 } else { step as f64 / steps as f64 };
+            round(start.0 + (other.q - self.q) as f64 * t,
+                  start.1 + (other.r - self.r) as f64 * t,
+                  start.2 + (other.s - self.s) as f64 * t)
+        });
+    }
+}
+
+// The nearest hex to a fractional position
+fn round(q: f64, r: f64, s: f64) -> Cube {
+    let (mut rounded_q, mut rounded_r, mut rounded_s) = (q.round(), r.round(), s.round());
+    let (q_diff, r_diff, s_diff) = ((rounded_q - q).abs(), (rounded_r - r).abs(), (rounded_s - s).abs());
+    // Rounding each one separately can break q + r + s = 0, fix whichever moved the most
+    if q_diff > r_diff && q_diff > s_diff {
+        rounded_q = -rounded_r - rounded_s;
+    } else if r_diff > s_diff {
+        rounded_r = -rounded_q - rounded_s;
+    } else {
+        rounded_s = -rounded_q - rounded_r;
+    }
+    return Cube { q: rounded_q as i32, r: rounded_r as i32, s: rounded_s as i32 };
+}
+
+impl Add for Cube {
+    type Output = Cube;
+
+    fn add(self, other: Cube) -> Cube {
+        return Cube { q: self.q + other.q, r: self.r + other.r, s: self.s + other.s };
+    }
+}
+
+impl Sub for Cube {
+    type Output = Cube;
+
+    fn sub(self, other: Cube) -> Cube {
+        return Cube { q: self.q - other.q, r: self.r - other.r, s: self.s - other.s };
+    }
+}
+
+impl Mul<i32> for Cube {
+    type Output = Cube;
+
+    fn mul(self, factor: i32) -> Cube {
+        return Cube { q: self.q * factor, r: self.r * factor, s: self.s * factor };
+    }
+}
+
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.q, self.r, self.s)
+    }
+}
+
+// A shortest route from one hex to another. Any offset is a sum of at most two adjacent
+// directions, so the canonical route is the longer run first, then the shorter one. Runs of the
+// same length are in clockwise order from the north.
+pub fn shortest_path(from: Cube, to: Cube) -> Vec<(Direction, u32)> {
+    let offset = to - from;
+    if offset == ORIGIN {
+        return Vec::new();
+    }
+    for i in 0..6 {
+        let (first, second) = (ALL_DIRECTIONS[i], ALL_DIRECTIONS[(i + 1) % 6]);
+        let (a, b) = (Cube::offset(first), Cube::offset(second));
+        // Solve offset = x * a + y * b, adjacent directions have a determinant of +-1
+        let determinant = a.q * b.r - a.r * b.q;
+        let x = (offset.q * b.r - offset.r * b.q) * determinant;
+        let y = (a.q * offset.r - a.r * offset.q) * determinant;
+        if x < 0 || y < 0 {
+            continue;
+        }
+        let mut runs: Vec<(Direction, u32)> =
+            vec![(first, x as u32), (second, y as u32)].into_iter().filter(|&(_, count)| count > 0).collect();
+        runs.sort_by_key(|&(direction, count)| {
+            (cmp::Reverse(count), ALL_DIRECTIONS.iter().position(|&d| d == direction))
+        });
+        return runs;
+    }
+    unreachable!("Every offset is between two adjacent directions");
+}
+
+// Formats a route like "3×ne, 2×n"
+pub fn format_path(path: &[(Direction, u32)]) -> String {
+    return path.iter().map(|&(direction, count)| format!("{}×{}", count, direction)).collect::<Vec<_>>().join(", ");
+}
+
+#[test]
+fn test_axes() {
+    for &direction in ALL_DIRECTIONS.iter() {
+        assert_eq!(Cube::offset(direction), Cube::from_axes(direction.step()), "{}", direction);
+    }
+    for a0 in -5..6 {
+        for a1 in -5..6 {
+            let cube = Cube::from_axes((a0, a1));
+            assert_eq!(0, cube.q + cube.r + cube.s);
+            assert_eq!((a0, a1), cube.to_axes());
+            let expected = cmp::max(cmp::max(a0.abs(), a1.abs()), (a1 - a0).abs());
+            assert_eq!(expected, cube.length());
+        }
+    }
+}
+
+#[test]
+fn test_ring_and_line() {
+    let centre = Cube::new(2, -5);
+    assert_eq!(vec![centre], centre.ring(0).collect::<Vec<_>>());
+    assert_eq!(centre.neighbors().collect::<Vec<_>>(), centre.ring(1).collect::<Vec<_>>());
+    for radius in 1..6 {
+        let ring: Vec<Cube> = centre.ring(radius).collect();
+        assert_eq!(6 * radius as usize, ring.len());
+        assert!(ring.iter().all(|&hex| hex.distance(centre) == radius as i32));
+        // Each hex is next to the one before, all the way around
+        for (i, &hex) in ring.iter().enumerate() {
+            assert_eq!(1, hex.distance(ring[(i + 1) % ring.len()]));
+        }
+    }
+
+    for &end in [Cube::new(0, 0), Cube::new(3, -1), Cube::new(-4, 4), Cube::new(5, 2), Cube::new(-2, -3)].iter() {
+        let line: Vec<Cube> = centre.line(end).collect();
+        assert_eq!(centre.distance(end) as usize + 1, line.len());
+        assert_eq!((Some(&centre), Some(&end)), (line.first(), line.last()));
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+    }
+}
+
+#[test]
+fn test_shortest_path() {
+    use direction::Direction::*;
+
+    assert_eq!(Vec::<(Direction, u32)>::new(), shortest_path(Cube::new(1, 1), Cube::new(1, 1)));
+    let to = Cube::offset(NE) * 3 + Cube::offset(N) * 2;
+    assert_eq!(vec![(NE, 3), (N, 2)], shortest_path(ORIGIN, to));
+    assert_eq!("3×ne, 2×n", format_path(&shortest_path(ORIGIN, to)));
+    assert_eq!(vec![(SW, 3), (S, 2)], shortest_path(to, ORIGIN));
+    assert_eq!(vec![(N, 2), (NE, 2)], shortest_path(ORIGIN, Cube::offset(N) * 2 + Cube::offset(NE) * 2));
+    assert_eq!(vec![(S, 4)], shortest_path(ORIGIN, Cube::offset(S) * 4));
+
+    for q in -4..5 {
+        for r in -4..5 {
+            let (from, to) = (Cube::new(1, -2), Cube::new(q, r));
+            let path = shortest_path(from, to);
+            let end = path.iter().fold(from, |hex, &(direction, count)| hex + Cube::offset(direction) * count as i32);
+            assert_eq!(to, end);
+            assert_eq!(from.distance(to) as u32, path.iter().map(|&(_, count)| count).sum::<u32>());
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#![allow(clippy::needless_return)]
+
+pub mod direction;
+pub mod hex;
+pub mod render;
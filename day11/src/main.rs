@@ -1,8 +1,18 @@
+#![allow(clippy::needless_return)]
+
+extern crate day11;
+
+use std::env;
 use std::fs::File;
-use std::io::prelude::*;
-use std::iter::FromIterator;
+use std::io::BufReader;
 use std::cmp;
 
+use day11::direction::DirectionReader;
+use day11::hex;
+use day11::hex::Cube;
+use day11::render;
+use day11::render::Walk;
+
 // The coordinates of a particular point is its location along the two diagonal axes, like this:
 //       ____      ____
 // \____/ 3 2\____/ 2 3\____/
@@ -20,46 +30,74 @@ use std::cmp;
 // Distance from zero to a particular point is the maximum distance along any of the 3 axes.  The
 // two diagonal axes are given by the coordinates, and the third (vertical) axis is axis2 - axis1.
 
-// Convert the input into a Vec of coordinate steps
-fn get_input() -> Vec<(i32, i32)> {
-    let mut f = File::open("input.txt").expect("Could not open file");
-    let mut input_str = String::new();
-    f.read_to_string(&mut input_str)
-        .expect("Could not read file");
-
-    return Vec::from_iter(
-        input_str
-            .split(',')
-            .map(|x| match x {
-                "n"  => (1, 1),
-                "ne" => (0, 1),
-                "se" => (-1, 0),
-                "s"  => (-1, -1),
-                "sw" => (0, -1),
-                "nw" => (1, 0),
-                text => panic!("Unexpected input {}", text)
-            }));
+// Reads the input a direction at a time, converting each into a coordinate step
+fn get_input() -> impl Iterator<Item = (i32, i32)> {
+    let f = File::open("input.txt").expect("Could not open file");
+    return DirectionReader::new(BufReader::new(f)).map(|direction| match direction {
+        Ok(direction) => direction.step(),
+        Err(error) => panic!("{}", error),
+    });
 }
 
 fn distance_from_zero(location: (i32, i32)) -> i32 {
-    let z = location.1 - location.0;
-    return cmp::max(cmp::max(location.0.abs(), location.1.abs()), z.abs());
+    return Cube::from_axes(location).length();
 }
 
-fn distances(steps: &[(i32, i32)]) -> (i32, i32) {
+// The distance at the end of the walk, the furthest distance along it and where it ended. Each
+// location along the way is passed to visit.
+fn distances<T, F>(steps: T, mut visit: F) -> (i32, i32, (i32, i32))
+where
+    T: IntoIterator<Item = (i32, i32)>,
+    F: FnMut((i32, i32)),
+{
     let mut location = (0, 0);
     let mut max_dist = 0;
-    for step in steps.iter() {
+    for step in steps {
         location.0 += step.0;
         location.1 += step.1;
         max_dist = cmp::max(max_dist, distance_from_zero(location));
+        visit(location);
     }
-    return (distance_from_zero(location), max_dist);
+    return (distance_from_zero(location), max_dist, location);
 }
 
+// Walks that fit in this many columns and rows are drawn as text by default
+const ASCII_COLUMNS: usize = 120;
+const ASCII_ROWS: usize = 60;
+
 fn main() {
     let input = get_input();
-    let (max_distance, end_distance) = distances(&input);
-    println!("Part 1: {}", max_distance);
-    println!("Part 2: {}", end_distance);
+    let render_mode = env::args().nth(1);
+    if let Some(mode) = render_mode {
+        let mut walk = Walk::new();
+        distances(input, |location| walk.visit(Cube::from_axes(location)));
+        let ascii = match mode.as_str() {
+            "render" => walk.fits(ASCII_COLUMNS, ASCII_ROWS),
+            "ascii" => true,
+            "svg" => false,
+            _ => panic!("Unknown render mode {}, expected render, ascii or svg", mode),
+        };
+        print!("{}", if ascii { render::render_ascii(&walk) } else { render::render_svg(&walk) });
+        return;
+    }
+
+    let (end_distance, max_distance, end) = distances(input, |_| ());
+    println!("Part 1: {}", end_distance);
+    println!("Part 2: {}", max_distance);
+    let route = hex::shortest_path(Cube::from_axes(end), hex::ORIGIN);
+    if !route.is_empty() {
+        println!("Route back: {}", hex::format_path(&route));
+    }
+}
+
+#[test]
+fn test_examples() {
+    let examples = [("ne,ne,ne", 3, "3×sw"), ("ne,ne,sw,sw", 0, ""), ("ne,ne,s,s", 2, "2×nw"),
+                    ("se,sw,se,sw,sw", 3, "2×n, 1×ne")];
+    for &(input, distance, route) in examples.iter() {
+        let steps = DirectionReader::new(input.as_bytes()).map(|direction| direction.unwrap().step());
+        let (end_distance, _, end) = distances(steps, |_| ());
+        assert_eq!(distance, end_distance);
+        assert_eq!(route, hex::format_path(&hex::shortest_path(Cube::from_axes(end), hex::ORIGIN)));
+    }
 }
@@ -0,0 +1,196 @@
+// Draws a walk on the hex grid, either as text or as an SVG image. North is up, like the puzzle.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use hex::{Cube, ORIGIN};
+
+// Every hex a walk visited, the furthest one from the start and where it ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct Walk {
+    pub visited: BTreeSet<Cube>,
+    pub farthest: Cube,
+    pub end: Cube,
+}
+
+impl Walk {
+    pub fn new() -> Walk {
+        let mut visited = BTreeSet::new();
+        visited.insert(ORIGIN);
+        return Walk { visited, farthest: ORIGIN, end: ORIGIN };
+    }
+
+    pub fn visit(&mut self, hex: Cube) {
+        self.visited.insert(hex);
+        if hex.length() > self.farthest.length() {
+            self.farthest = hex;
+        }
+        self.end = hex;
+    }
+
+    // The columns and doubled rows covered by the walk, see render_ascii
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        let (mut min_q, mut max_q, mut min_y, mut max_y) = (0, 0, 0, 0);
+        for hex in self.visited.iter() {
+            min_q = min_q.min(hex.q);
+            max_q = max_q.max(hex.q);
+            min_y = min_y.min(doubled_row(*hex));
+            max_y = max_y.max(doubled_row(*hex));
+        }
+        return (min_q, max_q, min_y, max_y);
+    }
+
+    // Whether the text drawing fits in the given number of columns and rows
+    pub fn fits(&self, columns: usize, rows: usize) -> bool {
+        let (min_q, max_q, min_y, max_y) = self.bounds();
+        return ((max_q - min_q) * 2 + 1) as usize <= columns && (max_y - min_y + 1) as usize <= rows;
+    }
+
+    fn marker(&self, hex: Cube) -> char {
+        if hex == ORIGIN {
+            return 'S';
+        } else if hex == self.end {
+            return 'E';
+        } else if hex == self.farthest {
+            return 'F';
+        } else if self.visited.contains(&hex) {
+            return '*';
+        }
+        return '.';
+    }
+
+    fn legend(&self) -> String {
+        return format!("S start, E end {} away, F farthest {} away, * visited",
+                       self.end.length(), self.farthest.length());
+    }
+}
+
+impl Default for Walk {
+    fn default() -> Walk {
+        return Walk::new();
+    }
+}
+
+// Each column of hexes is half a hex lower than the one to its west, so rows are counted in half
+// hexes. Going north takes 2 from the row and going north east takes 1.
+fn doubled_row(hex: Cube) -> i32 {
+    return 2 * hex.r + hex.q;
+}
+
+// Draws each hex as a single character, with the columns two characters apart and every other
+// line holding alternate columns. After ne,ne,s,s that is:
+// .   F
+//   *
+// S   *
+//   .
+// .   E
+pub fn render_ascii(walk: &Walk) -> String {
+    let (min_q, max_q, min_y, max_y) = walk.bounds();
+    let mut result = String::new();
+    for y in min_y..=max_y {
+        let mut line = String::new();
+        for q in min_q..=max_q {
+            // Only every other row has a hex in this column
+            if (y - q) % 2 != 0 {
+                line.push_str("  ");
+                continue;
+            }
+            line.push(walk.marker(Cube::new(q, (y - q) / 2)));
+            line.push(' ');
+        }
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result.push_str(&walk.legend());
+    result.push('\n');
+    return result;
+}
+
+const SVG_HEX_SIZE: f64 = 10.0;
+
+// The centre of a hex in the image, with flat topped hexes SVG_HEX_SIZE from centre to corner
+fn svg_centre(hex: Cube) -> (f64, f64) {
+    return (SVG_HEX_SIZE * 1.5 * hex.q as f64, SVG_HEX_SIZE * 3f64.sqrt() / 2.0 * doubled_row(hex) as f64);
+}
+
+fn svg_hex(output: &mut String, hex: Cube, class: &str) {
+    let (x, y) = svg_centre(hex);
+    let corners: Vec<String> = (0..6).map(|corner| {
+        let angle = (60.0 * corner as f64).to_radians();
+        format!("{:.1},{:.1}", x + SVG_HEX_SIZE * angle.cos(), y + SVG_HEX_SIZE * angle.sin())
+    }).collect();
+    writeln!(output, "<polygon class=\"{}\" points=\"{}\"/>", class, corners.join(" ")).unwrap();
+}
+
+// Draws the visited hexes, then the start, farthest and end hexes on top, with a legend below.
+// Large walks would make those three hexes too small to see, so they are also circled.
+pub fn render_svg(walk: &Walk) -> String {
+    let (min_q, max_q, min_y, max_y) = walk.bounds();
+    // Half a hex high, which is also the height of a doubled row
+    let half_height = SVG_HEX_SIZE * 3f64.sqrt() / 2.0;
+    let width = SVG_HEX_SIZE * (1.5 * (max_q - min_q) as f64 + 2.0);
+    let map_height = half_height * (max_y - min_y + 2) as f64;
+    let marker_radius = SVG_HEX_SIZE.max(width.max(map_height) / 100.0);
+    let font_size = marker_radius * 1.5;
+    let height = map_height + font_size * 2.0;
+    let (left, top) = (SVG_HEX_SIZE * (1.5 * min_q as f64 - 1.0), half_height * (min_y - 1) as f64);
+
+    let mut output = String::new();
+    writeln!(output, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">",
+             left, top, width, height).unwrap();
+    writeln!(output, "<style>polygon {{ stroke: #666; stroke-width: 1 }} .visited {{ fill: #ccc }} \
+                      .start {{ fill: #2a2 }} .farthest {{ fill: #d22 }} .end {{ fill: #22d }} \
+                      circle {{ fill: none; stroke-width: {:.1} }} text {{ font: {:.1}px sans-serif }}</style>",
+             marker_radius / 4.0, font_size).unwrap();
+    for &hex in walk.visited.iter() {
+        svg_hex(&mut output, hex, "visited");
+    }
+    for &(hex, class, colour) in [(walk.farthest, "farthest", "#d22"), (walk.end, "end", "#22d"),
+                                  (ORIGIN, "start", "#2a2")].iter() {
+        svg_hex(&mut output, hex, class);
+        let (x, y) = svg_centre(hex);
+        writeln!(output, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" stroke=\"{}\"/>",
+                 x, y, marker_radius, colour).unwrap();
+    }
+    writeln!(output, "<text x=\"{:.1}\" y=\"{:.1}\">Green start, blue end {} away, red farthest {} away</text>",
+             left + font_size / 2.0, top + height - font_size / 2.0, walk.end.length(), walk.farthest.length()).unwrap();
+    output.push_str("</svg>\n");
+    return output;
+}
+
+#[cfg(test)]
+fn walk(directions: &str) -> Walk {
+    use direction::DirectionReader;
+
+    let mut walk = Walk::new();
+    let mut hex = ORIGIN;
+    for direction in DirectionReader::new(directions.as_bytes()) {
+        hex = hex.neighbor(direction.unwrap());
+        walk.visit(hex);
+    }
+    return walk;
+}
+
+#[test]
+fn test_render_ascii() {
+    assert_eq!("S\nS start, E end 0 away, F farthest 0 away, * visited\n", render_ascii(&Walk::new()));
+
+    let walk = walk("ne,ne,s,s");
+    assert_eq!(Cube::new(2, -2), walk.farthest);
+    let expected = ".   F\n  *\nS   *\n  .\n.   E\nS start, E end 2 away, F farthest 2 away, * visited\n";
+    assert_eq!(expected, render_ascii(&walk));
+    assert!(walk.fits(5, 5));
+    assert!(!walk.fits(4, 5));
+    assert!(!walk.fits(5, 4));
+}
+
+#[test]
+fn test_render_svg() {
+    let walk = walk("n,n,se,s");
+    let svg = render_svg(&walk);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(walk.visited.len() + 3, svg.matches("<polygon").count());
+    assert_eq!(3, svg.matches("<circle").count());
+    assert!(svg.contains("blue end 1 away, red farthest 2 away"));
+}
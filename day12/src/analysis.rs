@@ -0,0 +1,270 @@
+// Structure of the pipe network beyond which programs can reach each other: the programs and pipes
+// that would split a group if removed, how many hops apart programs are, and Graphviz output.
+// Everything works on adjacency lists where each pipe is listed from both ends.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use graph::Graph;
+
+// Each group of connected programs, sorted, in order of their lowest program
+pub fn components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; graph.len()];
+    let mut result = Vec::new();
+    for start in 0..graph.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut component = vec![start];
+        let mut next = 0;
+        while next < component.len() {
+            let node = component[next];
+            next += 1;
+            for &neighbor in graph[node].iter() {
+                if !seen[neighbor] {
+                    seen[neighbor] = true;
+                    component.push(neighbor);
+                }
+            }
+        }
+        component.sort_unstable();
+        result.push(component);
+    }
+    return result;
+}
+
+// The number of hops from one program to every other, None for programs it can't reach
+pub fn hop_distances(graph: &[Vec<usize>], from: usize) -> Vec<Option<usize>> {
+    let mut distances = vec![None; graph.len()];
+    distances[from] = Some(0);
+    let mut next_nodes = VecDeque::new();
+    next_nodes.push_back(from);
+    while let Some(node) = next_nodes.pop_front() {
+        let distance = distances[node].unwrap() + 1;
+        for &neighbor in graph[node].iter() {
+            if distances[neighbor].is_none() {
+                distances[neighbor] = Some(distance);
+                next_nodes.push_back(neighbor);
+            }
+        }
+    }
+    return distances;
+}
+
+pub fn shortest_hops(graph: &[Vec<usize>], from: usize, to: usize) -> Option<usize> {
+    return hop_distances(graph, from)[to];
+}
+
+// The most hops between any two programs in the component, searching from every one of them
+pub fn diameter(graph: &[Vec<usize>], component: &[usize]) -> usize {
+    return component.iter()
+        .map(|&node| hop_distances(graph, node).into_iter().flatten().max().unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+}
+
+// Finds the cut points and bridges with a single depth first search of each component. A
+// program's low value is the earliest discovered program reachable from its subtree without
+// going back along the pipe it was reached by. If a child's low value isn't earlier than its
+// parent then the child's subtree only reaches the rest of the graph through the parent.
+fn find_cuts(graph: &[Vec<usize>]) -> (Vec<bool>, Vec<(usize, usize)>) {
+    const UNSEEN: usize = usize::MAX;
+    let mut discovered = vec![UNSEEN; graph.len()];
+    let mut low = vec![0; graph.len()];
+    let mut is_cut_point = vec![false; graph.len()];
+    let mut bridges = Vec::new();
+    let mut time = 0;
+
+    for root in 0..graph.len() {
+        if discovered[root] != UNSEEN {
+            continue;
+        }
+        discovered[root] = time;
+        low[root] = time;
+        time += 1;
+        let mut root_children = 0;
+        // The program, the one it was reached from, the next neighbor to look at and whether the
+        // pipe back to the parent has been skipped yet. Only one copy of a doubled pipe is skipped,
+        // the other is a second way back.
+        let mut stack: Vec<(usize, Option<usize>, usize, bool)> = vec![(root, None, 0, false)];
+        while let Some(&(node, parent, next, skipped_parent)) = stack.last() {
+            if next == graph[node].len() {
+                stack.pop();
+                if let Some(parent) = parent {
+                    low[parent] = low[parent].min(low[node]);
+                    if low[node] > discovered[parent] {
+                        bridges.push((parent.min(node), parent.max(node)));
+                    }
+                    if parent != root && low[node] >= discovered[parent] {
+                        is_cut_point[parent] = true;
+                    }
+                }
+                continue;
+            }
+
+            stack.last_mut().unwrap().2 += 1;
+            let neighbor = graph[node][next];
+            if Some(neighbor) == parent && !skipped_parent {
+                stack.last_mut().unwrap().3 = true;
+            } else if discovered[neighbor] == UNSEEN {
+                discovered[neighbor] = time;
+                low[neighbor] = time;
+                time += 1;
+                if node == root {
+                    root_children += 1;
+                }
+                stack.push((neighbor, Some(node), 0, false));
+            } else {
+                low[node] = low[node].min(discovered[neighbor]);
+            }
+        }
+        // The root has no parent to go through, it only splits the group if it has more than one
+        // subtree
+        is_cut_point[root] = root_children > 1;
+    }
+
+    bridges.sort_unstable();
+    return (is_cut_point, bridges);
+}
+
+// Programs whose removal would split their group, sorted
+pub fn articulation_points(graph: &[Vec<usize>]) -> Vec<usize> {
+    let (is_cut_point, _) = find_cuts(graph);
+    return (0..graph.len()).filter(|&node| is_cut_point[node]).collect();
+}
+
+// Pipes whose removal would split their group, each with the lower program first, sorted
+pub fn bridges(graph: &[Vec<usize>]) -> Vec<(usize, usize)> {
+    let (_, bridges) = find_cuts(graph);
+    return bridges;
+}
+
+fn escape_string(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+// Graphviz graph with a box around each component, articulation points filled in orange and
+// bridges drawn in red
+pub fn to_dot(graph: &Graph, components: &[Vec<usize>]) -> String {
+    let adjacency = graph.adjacency();
+    let (is_cut_point, bridges) = find_cuts(adjacency);
+    let mut output = String::new();
+    output.push_str("graph pipes {\n");
+    for (i, component) in components.iter().enumerate() {
+        writeln!(output, "    subgraph cluster_{} {{", i).unwrap();
+        let plural = if component.len() == 1 { "" } else { "s" };
+        writeln!(output, "        label=\"{} program{}\";", component.len(), plural).unwrap();
+        for &node in component.iter() {
+            let name = escape_string(graph.name(node));
+            if is_cut_point[node] {
+                writeln!(output, "        \"{}\" [style=filled, fillcolor=orange];", name).unwrap();
+            } else {
+                writeln!(output, "        \"{}\";", name).unwrap();
+            }
+        }
+        for &node in component.iter() {
+            // Each pipe once, from its lower end
+            for &neighbor in adjacency[node].iter().filter(|&&neighbor| neighbor >= node) {
+                write!(output, "        \"{}\" -- \"{}\"", escape_string(graph.name(node)),
+                       escape_string(graph.name(neighbor))).unwrap();
+                if bridges.binary_search(&(node, neighbor)).is_ok() {
+                    output.push_str(" [color=red, penwidth=2]");
+                }
+                output.push_str(";\n");
+            }
+        }
+        output.push_str("    }\n");
+    }
+    output.push_str("}\n");
+    return output;
+}
+
+#[cfg(test)]
+fn from_pipes(node_count: usize, pipes: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut graph = vec![Vec::new(); node_count];
+    for &(a, b) in pipes.iter() {
+        graph[a].push(b);
+        if a != b {
+            graph[b].push(a);
+        }
+    }
+    return graph;
+}
+
+#[test]
+fn test_analysis() {
+    // Two triangles joined by 2 - 3, with 6 hanging off the second, 7 on its own and 8 - 9
+    let pipes = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3), (5, 6), (7, 7), (8, 9)];
+    let graph = from_pipes(10, &pipes);
+    assert_eq!(vec![vec![0, 1, 2, 3, 4, 5, 6], vec![7], vec![8, 9]], components(&graph));
+    assert_eq!(vec![2, 3, 5], articulation_points(&graph));
+    assert_eq!(vec![(2, 3), (5, 6), (8, 9)], bridges(&graph));
+    assert_eq!(Some(4), shortest_hops(&graph, 0, 6));
+    assert_eq!(Some(0), shortest_hops(&graph, 7, 7));
+    assert_eq!(None, shortest_hops(&graph, 0, 7));
+    let diameters: Vec<usize> = components(&graph).iter().map(|component| diameter(&graph, component)).collect();
+    assert_eq!(vec![4, 0, 1], diameters);
+
+    // A doubled pipe is two ways across, so it isn't a bridge
+    let graph = from_pipes(3, &[(0, 1), (0, 1), (1, 2)]);
+    assert_eq!(vec![(1, 2)], bridges(&graph));
+    assert_eq!(vec![1], articulation_points(&graph));
+}
+
+#[test]
+fn test_cuts_match_removal() {
+    // Removing each program or pipe in turn and counting groups gives the same answers
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    for &(node_count, pipe_count) in [(12, 10), (20, 20), (30, 35)].iter() {
+        let pipes: Vec<(usize, usize)> = (0..pipe_count).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let a = (state >> 33) as usize % node_count;
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (a, (state >> 33) as usize % node_count)
+        }).collect();
+        let graph = from_pipes(node_count, &pipes);
+        let group_count = components(&graph).len();
+
+        let expected_points: Vec<usize> = (0..node_count).filter(|&removed| {
+            let without: Vec<(usize, usize)> =
+                pipes.iter().cloned().filter(|&(a, b)| a != removed && b != removed).collect();
+            // Not counting the removed program, which is left in a group of its own
+            let remaining_groups = components(&from_pipes(node_count, &without)).len() - 1;
+            let was_alone = graph[removed].iter().all(|&neighbor| neighbor == removed);
+            remaining_groups > group_count - was_alone as usize
+        }).collect();
+        assert_eq!(expected_points, articulation_points(&graph));
+
+        let mut expected_bridges: Vec<(usize, usize)> = (0..pipes.len()).filter(|&removed| {
+            let without: Vec<(usize, usize)> =
+                pipes.iter().enumerate().filter(|&(i, _)| i != removed).map(|(_, &pipe)| pipe).collect();
+            components(&from_pipes(node_count, &without)).len() > group_count
+        }).map(|i| (pipes[i].0.min(pipes[i].1), pipes[i].0.max(pipes[i].1))).collect();
+        expected_bridges.sort_unstable();
+        assert_eq!(expected_bridges, bridges(&graph));
+    }
+}
+
+#[test]
+fn test_to_dot() {
+    let (graph, _) = Graph::parse("a <-> b\nb <-> a, c\nc <-> b\n\"d\" <-> \"d\"\n").unwrap();
+    let expected = "\
+graph pipes {
+    subgraph cluster_0 {
+        label=\"3 programs\";
+        \"a\";
+        \"b\" [style=filled, fillcolor=orange];
+        \"c\";
+        \"a\" -- \"b\" [color=red, penwidth=2];
+        \"b\" -- \"c\" [color=red, penwidth=2];
+    }
+    subgraph cluster_1 {
+        label=\"1 program\";
+        \"\\\"d\\\"\";
+        \"\\\"d\\\"\" -- \"\\\"d\\\"\";
+    }
+}
+";
+    assert_eq!(expected, to_dot(&graph, &components(graph.adjacency())));
+}
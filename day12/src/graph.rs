@@ -0,0 +1,158 @@
+// Loads the pipe network from lines like "a <-> b, c". Programs can have any name, lines can be
+// in any order and a program can have more than one line. Pipes go both ways, so a pipe only
+// listed on one side is still added in both directions but reported as an asymmetry.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+    // Sorted and without duplicates
+    adjacency: Vec<Vec<usize>>,
+}
+
+// A pipe listed by one program that isn't listed back by the other
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asymmetry {
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for Asymmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} lists {} but {} doesn't list {}", self.from, self.to, self.to, self.from)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    // Counted from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl Graph {
+    // Programs are numbered in the order of their first line, so numbered programs listed in order
+    // keep their numbers. Programs without a line of their own come after the rest.
+    pub fn parse(input: &str) -> Result<(Graph, Vec<Asymmetry>), ParseError> {
+        let mut graph = Graph { names: Vec::new(), indices: HashMap::new(), adjacency: Vec::new() };
+        let mut lines = Vec::new();
+        for (line_index, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: String| ParseError { line: line_index + 1, message };
+            let separator = line.find("<->").ok_or_else(|| error("Expected '<->'".to_string()))?;
+            let from = line[..separator].trim();
+            if from.is_empty() {
+                return Err(error("Missing program name before '<->'".to_string()));
+            }
+            let neighbors = line[separator + 3..].trim();
+            let neighbors: Vec<&str> =
+                if neighbors.is_empty() { Vec::new() } else { neighbors.split(',').map(|name| name.trim()).collect() };
+            if let Some(i) = neighbors.iter().position(|name| name.is_empty()) {
+                return Err(error(format!("Missing program name {} after '<->'", i + 1)));
+            }
+            lines.push((graph.add_node(from), neighbors));
+        }
+
+        // Every pipe as it was listed, in order, and the same as a set to look for the reverse
+        let mut listed = Vec::new();
+        let mut listed_set = HashSet::new();
+        for (from, neighbors) in lines {
+            for to in neighbors {
+                let to = graph.add_node(to);
+                if listed_set.insert((from, to)) {
+                    listed.push((from, to));
+                }
+            }
+        }
+
+        let mut asymmetries = Vec::new();
+        for &(from, to) in listed.iter() {
+            graph.adjacency[from].push(to);
+            if from == to {
+                continue;
+            }
+            graph.adjacency[to].push(from);
+            if !listed_set.contains(&(to, from)) {
+                asymmetries.push(Asymmetry { from: graph.names[from].clone(), to: graph.names[to].clone() });
+            }
+        }
+        for neighbors in graph.adjacency.iter_mut() {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+        return Ok((graph, asymmetries));
+    }
+
+    fn add_node(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        let index = self.names.len();
+        self.names.push(name.to_string());
+        self.indices.insert(name.to_string(), index);
+        self.adjacency.push(Vec::new());
+        return index;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.names.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.names.is_empty();
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        return &self.names[index];
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        return self.indices.get(name).cloned();
+    }
+
+    // The neighbors of each program, by index
+    pub fn adjacency(&self) -> &[Vec<usize>] {
+        return &self.adjacency;
+    }
+}
+
+#[test]
+fn test_parse() {
+    let (graph, asymmetries) = Graph::parse("0 <-> 2\n1 <-> 1\n2 <-> 0, 3, 4\n3 <-> 2, 4\n4 <-> 2, 3\n").unwrap();
+    assert_eq!(vec![vec![2], vec![1], vec![0, 3, 4], vec![2, 4], vec![2, 3]], graph.adjacency());
+    assert_eq!(Vec::<Asymmetry>::new(), asymmetries);
+    assert_eq!(Some(3), graph.index("3"));
+
+    // Out of order, named, repeated and one sided
+    let input = "\
+        bob <-> alice, carol\n\
+        \n\
+        carol <-> bob, bob\n\
+        alice <-> dave\n\
+        bob <-> alice\n\
+        erin <->\n";
+    let (graph, asymmetries) = Graph::parse(input).unwrap();
+    assert_eq!(5, graph.len());
+    assert_eq!(vec!["bob", "carol", "alice", "erin", "dave"], (0..5).map(|i| graph.name(i)).collect::<Vec<_>>());
+    assert_eq!(vec![vec![1, 2], vec![0], vec![0, 4], vec![], vec![2]], graph.adjacency());
+    let asymmetries: Vec<String> = asymmetries.iter().map(|a| a.to_string()).collect();
+    assert_eq!(vec!["bob lists alice but alice doesn't list bob", "alice lists dave but dave doesn't list alice"],
+               asymmetries);
+
+    let errors = [("0 -> 1", "Line 1: Expected '<->'"), ("0 <-> 1\n <-> 1", "Line 2: Missing program name before '<->'"),
+                  ("0 <-> 1,,2", "Line 1: Missing program name 2 after '<->'")];
+    for &(input, message) in errors.iter() {
+        assert_eq!(Err(message.to_string()), Graph::parse(input).map(|_| ()).map_err(|e| e.to_string()));
+    }
+}
@@ -0,0 +1,5 @@
+#![allow(clippy::needless_return)]
+
+pub mod union_find;
+pub mod graph;
+pub mod analysis;
@@ -1,42 +1,53 @@
+#![cfg_attr(feature = "bench", feature(test))]
+#![allow(clippy::needless_return)]
+
+#[cfg(feature = "bench")]
+extern crate test;
+extern crate day12;
+
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::collections::vec_deque;
 
-// Get the input graph as an adjacency list
-fn get_input() -> Vec<Vec<usize>> {
+#[cfg(feature = "bench")]
+use test::Bencher;
+
+use day12::analysis;
+use day12::graph::Graph;
+use day12::union_find::UnionFind;
+
+// Get the input graph, warning about any pipes only listed on one side
+fn get_input() -> Graph {
     let mut f = File::open("input.txt").expect("Could not open file");
     let mut input_str = String::new();
     f.read_to_string(&mut input_str)
         .expect("Could not read file");
 
-    let mut result: Vec<Vec<usize>> = Vec::new();
-
-    for (i, line) in input_str.lines().enumerate() {
-        let mut numbers_iter = line.split(|c: char| !c.is_digit(10))
-            .filter(|x| x.len() > 0)
-            .map(|x| x.parse::<usize>().expect("Not a number??"));
-        let first_number = numbers_iter.next().expect("No first number?");
-        assert!(first_number == i, "Expected index {}, got {}", i, first_number);
-
-        result.push(numbers_iter.collect());
+    let (graph, asymmetries) = Graph::parse(&input_str).unwrap_or_else(|e| panic!("Malformed input: {}", e));
+    for asymmetry in asymmetries.iter() {
+        eprintln!("Warning: {}", asymmetry);
     }
-
-    return result;
+    return graph;
 }
 
 // Returns a vector where each element is the size of a group in the graph.  The first entry will
-// be the group that contains Node 0 (so the answer to part 1)
-fn get_group_sizes(graph: &Vec<Vec<usize>>) -> Vec<usize> {
+// be the group that contains the first node (so the answer to part 1), then the rest are in order
+// of their lowest node.
+fn get_group_sizes(graph: &[Vec<usize>], first: usize) -> Vec<usize> {
     let mut seen_node = vec![false; graph.len()];
     let mut next_nodes = vec_deque::VecDeque::with_capacity(10);
     let mut group_sizes = Vec::new();
-    while let Some((i, &_)) = seen_node.iter().enumerate().find(|&(_, &x)| !x) {
-        assert!(next_nodes.len() == 0);
+    // Every node before first_unseen has been seen, so each search for the next group carries on
+    // from the last one instead of starting over
+    let mut first_unseen = 0;
+    let mut next_group = Some(first);
+    while let Some(i) = next_group {
+        assert!(next_nodes.is_empty());
         next_nodes.push_back(i);
         seen_node[i] = true;
         let mut group_size = 1;
-        while next_nodes.len() > 0 {
-            let node_index = next_nodes.pop_front().unwrap();
+        while let Some(node_index) = next_nodes.pop_front() {
             for &child in graph[node_index].iter() {
                 if !seen_node[child] {
                     seen_node[child] = true;
@@ -46,14 +57,160 @@ fn get_group_sizes(graph: &Vec<Vec<usize>>) -> Vec<usize> {
             }
         }
         group_sizes.push(group_size);
+        next_group = (first_unseen..graph.len()).find(|&i| !seen_node[i]);
+        first_unseen = next_group.map_or(graph.len(), |i| i + 1);
     }
 
     return group_sizes;
 }
 
+// The same as get_group_sizes, connecting the pipes one at a time
+fn get_group_sizes_union_find(graph: &[Vec<usize>], first: usize) -> Vec<usize> {
+    let mut groups = UnionFind::new(graph.len());
+    for (node, children) in graph.iter().enumerate() {
+        for &child in children.iter() {
+            groups.connect(node, child);
+        }
+    }
+    let mut counted = vec![false; graph.len()];
+    let mut group_sizes = Vec::with_capacity(groups.group_count());
+    for node in Some(first).into_iter().chain(0..graph.len()) {
+        let root = groups.find(node);
+        if !counted[root] {
+            counted[root] = true;
+            group_sizes.push(groups.group_size(root));
+        }
+    }
+    return group_sizes;
+}
+
+// A graph with node_count nodes and about edge_count random pipes, in both directions
+#[cfg(any(test, feature = "bench"))]
+fn random_graph(node_count: usize, edge_count: usize) -> Vec<Vec<usize>> {
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as usize % node_count
+    };
+    let mut graph = vec![Vec::new(); node_count];
+    for _ in 0..edge_count {
+        let (a, b) = (next(), next());
+        graph[a].push(b);
+        graph[b].push(a);
+    }
+    return graph;
+}
+
+fn get_index(graph: &Graph, name: &str) -> usize {
+    return graph.index(name).unwrap_or_else(|| panic!("No program {}", name));
+}
+
+fn print_analysis(graph: &Graph) {
+    let adjacency = graph.adjacency();
+    let names = |nodes: &mut dyn Iterator<Item = usize>| nodes.map(|node| graph.name(node)).collect::<Vec<_>>().join(", ");
+
+    let articulation_points = analysis::articulation_points(adjacency);
+    println!("Articulation points ({}): {}", articulation_points.len(), names(&mut articulation_points.iter().cloned()));
+    let bridges = analysis::bridges(adjacency);
+    let bridge_names: Vec<String> =
+        bridges.iter().map(|&(a, b)| format!("{} <-> {}", graph.name(a), graph.name(b))).collect();
+    println!("Bridges ({}): {}", bridges.len(), bridge_names.join(", "));
+
+    let mut components = analysis::components(adjacency);
+    components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+    println!("Groups ({}), largest first, named by their lowest program:", components.len());
+    for component in components.iter().filter(|component| component.len() > 1) {
+        println!("  {}: {} programs, diameter {}", graph.name(component[0]), component.len(),
+                 analysis::diameter(adjacency, component));
+    }
+    let singles = components.iter().filter(|component| component.len() == 1).count();
+    println!("  and {} programs on their own", singles);
+}
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = args.first().map(|arg| arg.as_str());
     let input_graph = get_input();
-    let group_sizes = get_group_sizes(&input_graph);
-    println!("Part 1: {}", group_sizes[0]);
-    println!("Part 2: {}", group_sizes.len());
+    match mode {
+        Some("analyze") => print_analysis(&input_graph),
+        Some("hops") => {
+            if args.len() != 3 {
+                panic!("Expected hops FROM TO");
+            }
+            let (from, to) = (get_index(&input_graph, &args[1]), get_index(&input_graph, &args[2]));
+            match analysis::shortest_hops(input_graph.adjacency(), from, to) {
+                Some(hops) => println!("{} hops from {} to {}", hops, args[1], args[2]),
+                None => println!("{} can't reach {}", args[1], args[2]),
+            }
+        }
+        Some("dot") => {
+            // Every group, or only the one containing the given program
+            let mut components = analysis::components(input_graph.adjacency());
+            if let Some(name) = args.get(1) {
+                let node = get_index(&input_graph, name);
+                components.retain(|component| component.binary_search(&node).is_ok());
+            }
+            print!("{}", analysis::to_dot(&input_graph, &components));
+        }
+        None | Some("bfs") | Some("union-find") => {
+            // Searching the whole graph at once is faster when it is all known up front, union
+            // find is for adding pipes as they come
+            let group_sizes_fn: fn(&[Vec<usize>], usize) -> Vec<usize> =
+                if mode == Some("union-find") { get_group_sizes_union_find } else { get_group_sizes };
+            let group_sizes = group_sizes_fn(input_graph.adjacency(), get_index(&input_graph, "0"));
+            println!("Part 1: {}", group_sizes[0]);
+            println!("Part 2: {}", group_sizes.len());
+        }
+        Some(other) => panic!("Unknown mode {}, expected bfs, union-find, analyze, hops or dot", other),
+    }
+}
+
+#[test]
+fn test_group_sizes() {
+    let example = vec![vec![2], vec![1], vec![0, 3, 4], vec![2, 4], vec![2, 3, 6], vec![6], vec![4, 5]];
+    assert_eq!(vec![6, 1], get_group_sizes(&example, 0));
+    assert_eq!(vec![6, 1], get_group_sizes_union_find(&example, 0));
+    assert_eq!(vec![1, 6], get_group_sizes(&example, 1));
+    assert_eq!(vec![1, 6], get_group_sizes_union_find(&example, 1));
+
+    for &(node_count, edge_count) in [(1, 0), (100, 30), (1000, 700), (1000, 2000)].iter() {
+        let graph = random_graph(node_count, edge_count);
+        for &first in [0, node_count / 2, node_count - 1].iter() {
+            assert_eq!(get_group_sizes(&graph, first), get_group_sizes_union_find(&graph, first));
+        }
+    }
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn bfs_bench(b: &mut Bencher) {
+    let graph = random_graph(1_000_000, 700_000);
+    b.iter(|| test::black_box(get_group_sizes(&graph, 0)));
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn union_find_bench(b: &mut Bencher) {
+    let graph = random_graph(1_000_000, 700_000);
+    b.iter(|| test::black_box(get_group_sizes_union_find(&graph, 0)));
+}
+
+// Adds pipes one at a time, checking after each whether nodes 0 and 1 are connected yet
+#[cfg(feature = "bench")]
+#[bench]
+fn union_find_incremental_bench(b: &mut Bencher) {
+    let graph = random_graph(1_000_000, 700_000);
+    b.iter(|| {
+        let mut groups = UnionFind::new(graph.len());
+        let mut connected_after = None;
+        for (node, children) in graph.iter().enumerate() {
+            for &child in children.iter() {
+                groups.connect(node, child);
+                if connected_after.is_none() && groups.same_group(0, 1) {
+                    connected_after = Some(node);
+                }
+            }
+        }
+        test::black_box((connected_after, groups.group_count()))
+    });
 }
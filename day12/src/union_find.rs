@@ -0,0 +1,121 @@
+// Disjoint sets of nodes, for finding groups as pipes are added one at a time. Each set is a tree
+// stored as parent links. Finding a root points every node on the way straight at it, and the
+// shallower tree goes under the deeper one when joining, so operations are close to constant
+// time.
+pub struct UnionFind {
+    parent: Vec<usize>,
+    // Upper bound on the height of each root's tree
+    rank: Vec<u8>,
+    // Number of nodes in each root's tree
+    size: Vec<usize>,
+    group_count: usize,
+}
+
+impl UnionFind {
+    // Every node starts in a group of its own
+    pub fn new(node_count: usize) -> UnionFind {
+        return UnionFind {
+            parent: (0..node_count).collect(),
+            rank: vec![0; node_count],
+            size: vec![1; node_count],
+            group_count: node_count,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.parent.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.parent.is_empty();
+    }
+
+    // The node representing the group, the same for every node in the group until it changes
+    pub fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut node = node;
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        return root;
+    }
+
+    // Joins the groups of the two nodes, returning false if they were already in the same group
+    pub fn connect(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (child, root) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        if self.rank[child] == self.rank[root] {
+            self.rank[root] += 1;
+        }
+        self.group_count -= 1;
+        return true;
+    }
+
+    pub fn same_group(&mut self, a: usize, b: usize) -> bool {
+        return self.find(a) == self.find(b);
+    }
+
+    pub fn group_size(&mut self, node: usize) -> usize {
+        let root = self.find(node);
+        return self.size[root];
+    }
+
+    pub fn group_count(&self) -> usize {
+        return self.group_count;
+    }
+
+    // The size of every group, ordered by the lowest node in each, so the group containing node 0
+    // comes first
+    pub fn group_sizes(&mut self) -> Vec<usize> {
+        let mut counted = vec![false; self.len()];
+        let mut result = Vec::with_capacity(self.group_count);
+        for node in 0..self.len() {
+            let root = self.find(node);
+            if !counted[root] {
+                counted[root] = true;
+                result.push(self.size[root]);
+            }
+        }
+        return result;
+    }
+}
+
+#[test]
+fn test_union_find() {
+    let mut groups = UnionFind::new(8);
+    assert_eq!(8, groups.group_count());
+    assert!(groups.connect(0, 5));
+    assert!(groups.connect(5, 3));
+    assert!(!groups.connect(3, 0));
+    assert!(groups.connect(6, 7));
+    assert!(groups.same_group(0, 3));
+    assert!(!groups.same_group(0, 6));
+    assert_eq!(3, groups.group_size(5));
+    assert_eq!(1, groups.group_size(1));
+    assert_eq!(5, groups.group_count());
+    assert_eq!(vec![3, 1, 1, 1, 2], groups.group_sizes());
+
+    assert!(groups.connect(7, 3));
+    assert!(groups.same_group(6, 0));
+    assert_eq!(vec![5, 1, 1, 1], groups.group_sizes());
+    assert_eq!(4, groups.group_count());
+
+    // A long chain still finds the same root from both ends
+    let mut chain = UnionFind::new(1000);
+    for i in 1..1000 {
+        chain.connect(i - 1, i);
+    }
+    assert!(chain.same_group(0, 999));
+    assert_eq!(vec![1000], chain.group_sizes());
+    assert!(UnionFind::new(0).is_empty());
+}
@@ -0,0 +1,147 @@
+// Finds the smallest delay that gets through the firewall without trying every delay. A scanner
+// with range r is back at the top every 2 * (r - 1) picoseconds, its period, and the packet reaches
+// it depth picoseconds after leaving. So the packet is caught exactly when
+// delay ≡ -depth (mod period), and each scanner forbids one residue class.
+//
+// Scanners are combined into a wheel: the delays allowed by every scanner so far, as residues
+// modulo the LCM of their periods. Adding a period multiplies the residues by how much the LCM
+// grows and then throws out the newly forbidden ones. Periods that would make the wheel too big
+// are left out of it, and the wheel's delays are checked against those in increasing order
+// instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+// Most residues the wheel may hold, about a megabyte of them
+const RESIDUE_LIMIT: u64 = 1 << 17;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        return a;
+    }
+    return gcd(b, a % b);
+}
+
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    return (a / gcd(a, b)).checked_mul(b);
+}
+
+// The smallest delay that isn't caught, or None if every delay is
+pub fn min_delay(firewall: &[(u64, u64)]) -> Option<u64> {
+    return min_delay_with_limit(firewall, RESIDUE_LIMIT);
+}
+
+pub fn min_delay_with_limit(firewall: &[(u64, u64)], residue_limit: u64) -> Option<u64> {
+    // The forbidden residues of each period, smallest period first
+    let mut forbidden: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+    for &(depth, range) in firewall.iter() {
+        if range <= 1 {
+            // Never leaves the top, so it catches everything
+            return None;
+        }
+        let period = 2 * (range - 1);
+        forbidden.entry(period).or_default().insert((period - depth % period) % period);
+    }
+
+    let mut modulus = 1;
+    let mut allowed: Vec<u64> = vec![0];
+    // A period the whole firewall repeats with, None if it doesn't fit in a u64
+    let mut full_period = Some(1);
+    let mut deferred = Vec::new();
+    for (period, residues) in forbidden {
+        if residues.len() as u64 == period {
+            return None;
+        }
+        full_period = full_period.and_then(|full_period| lcm(full_period, period));
+        let new_modulus = match lcm(modulus, period) {
+            Some(new_modulus) => new_modulus,
+            None => {
+                deferred.push((period, residues));
+                continue;
+            }
+        };
+        let factor = new_modulus / modulus;
+        if (allowed.len() as u64).checked_mul(factor).is_none_or(|size| size > residue_limit) {
+            deferred.push((period, residues));
+            continue;
+        }
+        // Stays sorted as each copy of the old residues is higher than the one before
+        allowed = (0..factor)
+            .flat_map(|copy| allowed.iter().map(move |&residue| copy * modulus + residue))
+            .filter(|residue| !residues.contains(&(residue % period)))
+            .collect();
+        modulus = new_modulus;
+        if allowed.is_empty() {
+            return None;
+        }
+    }
+
+    // Checking a whole full period without finding a delay means there isn't one
+    let mut base: u64 = 0;
+    while full_period.is_none_or(|full_period| base < full_period) {
+        for &residue in allowed.iter() {
+            let delay = base.checked_add(residue)?;
+            if deferred.iter().all(|&(period, ref residues)| !residues.contains(&(delay % period))) {
+                return Some(delay);
+            }
+        }
+        base = base.checked_add(modulus)?;
+    }
+    return None;
+}
+
+// A firewall where scanners of the given ranges rule out every delay except those congruent to
+// target modulo each period
+#[cfg(test)]
+fn firewall_allowing(target: u64, ranges: &[u64]) -> Vec<(u64, u64)> {
+    let mut firewall = Vec::new();
+    let mut depth = 0;
+    for &range in ranges.iter() {
+        let period = 2 * (range - 1);
+        for residue in (0..period).filter(|&residue| residue != target % period) {
+            // Skip ahead to the next depth that forbids this residue
+            depth += (period + (period - residue) % period - depth % period) % period;
+            firewall.push((depth, range));
+            depth += 1;
+        }
+    }
+    return firewall;
+}
+
+#[test]
+fn test_min_delay() {
+    assert_eq!(Some(10), min_delay(&[(0, 3), (1, 2), (4, 4), (6, 4)]));
+    assert_eq!(Some(0), min_delay(&[]));
+    assert_eq!(None, min_delay(&[(3, 1)]));
+    assert_eq!(None, min_delay(&[(0, 2), (1, 2)]));
+    // Allowed by each on its own, but not together
+    assert_eq!(None, min_delay(&[(0, 2), (3, 3), (1, 3)]));
+
+    // Only one delay in every 12.9 billion gets through
+    let target = 9_876_543_210;
+    let firewall = firewall_allowing(target, &[3, 4, 6, 8, 12, 14, 18, 20, 24, 30]);
+    assert_eq!(Some(target), min_delay(&firewall));
+    // With a small wheel most of the periods are checked one delay at a time
+    assert_eq!(Some(target), min_delay_with_limit(&firewall, 1 << 12));
+}
+
+#[test]
+fn test_min_delay_matches_brute_force() {
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    let mut next = |limit: u64| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) % limit
+    };
+    for _ in 0..200 {
+        let mut firewall = Vec::new();
+        let mut depth = 0;
+        for _ in 0..next(12) {
+            depth += next(4);
+            firewall.push((depth, 2 + next(8)));
+            depth += 1;
+        }
+        let expected = super::compute_min_delay_brute_force(&firewall, 1_000_000);
+        for &limit in [1, 16, RESIDUE_LIMIT].iter() {
+            assert_eq!(expected, min_delay_with_limit(&firewall, limit), "{:?}", firewall);
+        }
+    }
+}
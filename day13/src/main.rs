@@ -1,18 +1,31 @@
+#![cfg_attr(feature = "bench", feature(test))]
+#![allow(clippy::needless_return)]
+
+#[cfg(feature = "bench")]
+extern crate test;
+
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 
-fn get_input() -> Vec<(u32, u32)> {
+mod delay;
+mod visualize;
+
+#[cfg(feature = "bench")]
+use test::Bencher;
+
+fn get_input() -> Vec<(u64, u64)> {
     let mut f = File::open("input.txt").expect("Could not open file");
     let mut input_str = String::new();
     f.read_to_string(&mut input_str)
         .expect("Could not read file");
 
-    let mut result: Vec<(u32, u32)> = Vec::new();
+    let mut result: Vec<(u64, u64)> = Vec::new();
 
     for line in input_str.lines() {
-        let mut numbers_iter = line.split(|c: char| !c.is_digit(10))
-            .filter(|x| x.len() > 0)
-            .map(|x| x.parse::<u32>().expect("Not a number??"));
+        let mut numbers_iter = line.split(|c: char| !c.is_ascii_digit())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<u64>().expect("Not a number??"));
         let index = numbers_iter.next().expect("No index?");
         let height = numbers_iter.next().expect("No height?");
         result.push((index, height));
@@ -21,7 +34,7 @@ fn get_input() -> Vec<(u32, u32)> {
     return result;
 }
 
-fn scan_position_from_time(range: u32, picoseconds: u32) -> u32 {
+fn scan_position_from_time(range: u64, picoseconds: u64) -> u64 {
     if range == 1 {
         return 0;
     }
@@ -33,7 +46,7 @@ fn scan_position_from_time(range: u32, picoseconds: u32) -> u32 {
     return state_count - wrapped_state;
 }
 
-fn compute_severity(firewall: &[(u32, u32)]) -> u32 {
+fn compute_severity(firewall: &[(u64, u64)]) -> u64 {
     let mut position = 0;
     let mut severity = 0;
     for &(depth, range) in firewall.iter() {
@@ -47,7 +60,7 @@ fn compute_severity(firewall: &[(u32, u32)]) -> u32 {
     return severity;
 }
 
-fn passes_firewall(firewall: &[(u32, u32)], delay: u32) -> bool {
+fn passes_firewall(firewall: &[(u64, u64)], delay: u64) -> bool {
     let mut position = 0;
     let mut picoseconds = delay;
     for &(depth, range) in firewall.iter() {
@@ -63,18 +76,66 @@ fn passes_firewall(firewall: &[(u32, u32)], delay: u32) -> bool {
     return true;
 }
 
-fn compute_min_delay(firewall: &[(u32, u32)]) -> u32 {
-    let mut delay = 0;
-    loop {
-        if passes_firewall(firewall, delay) {
-            return delay;
+// Tries every delay up to max_delay in turn
+fn compute_min_delay_brute_force(firewall: &[(u64, u64)], max_delay: u64) -> Option<u64> {
+    return (0..=max_delay).find(|&delay| passes_firewall(firewall, delay));
+}
+
+// The delay in the argument at index, or the default if there isn't one
+fn parse_delay(args: &[String], index: usize, default: u64) -> u64 {
+    return match args.get(index) {
+        Some(arg) => arg.parse().unwrap_or_else(|e| panic!("Invalid delay {}: {}", arg, e)),
+        None => default,
+    };
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = args.first().map(|arg| arg.as_str());
+    let firewall = get_input();
+    match mode {
+        Some("show") => {
+            let delay = parse_delay(&args, 1, 0);
+            print!("{}", visualize::render_trip(&firewall, delay));
+            print!("{}", visualize::severity_breakdown(&firewall, delay));
+        }
+        Some("severity") => {
+            let from = parse_delay(&args, 1, 0);
+            let to = parse_delay(&args, 2, from + 20);
+            print!("{}", visualize::severity_table(&firewall, from..=to));
+        }
+        None | Some("sieve") | Some("brute-force") => {
+            println!("Part 1: {}", compute_severity(&firewall));
+            // The residue sieve is used unless brute-force is given
+            let min_delay = if mode == Some("brute-force") {
+                compute_min_delay_brute_force(&firewall, u64::MAX)
+            } else {
+                delay::min_delay(&firewall)
+            };
+            println!("Part 2: {}", min_delay.expect("Every delay is caught"));
         }
-        delay += 1;
+        Some(other) => panic!("Unknown mode {}, expected sieve, brute-force, show or severity", other),
     }
 }
 
-fn main() {
+#[test]
+fn test_example() {
+    let firewall = [(0, 3), (1, 2), (4, 4), (6, 4)];
+    assert_eq!(24, compute_severity(&firewall));
+    assert_eq!(Some(10), compute_min_delay_brute_force(&firewall, 100));
+    assert_eq!(None, compute_min_delay_brute_force(&firewall, 9));
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn brute_force_min_delay_bench(b: &mut Bencher) {
+    let firewall = get_input();
+    b.iter(|| test::black_box(compute_min_delay_brute_force(&firewall, u64::MAX)));
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn sieve_min_delay_bench(b: &mut Bencher) {
     let firewall = get_input();
-    println!("Part 1: {}", compute_severity(&firewall));
-    println!("Part 2: {}", compute_min_delay(&firewall));
+    b.iter(|| test::black_box(delay::min_delay(&firewall)));
 }
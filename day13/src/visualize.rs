@@ -0,0 +1,129 @@
+// Shows the packet's trip through the firewall the way the puzzle draws it, one picture for each
+// picosecond as the packet enters a layer, plus tables of where it gets caught and how badly.
+
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+use super::scan_position_from_time;
+
+// The layers that catch the packet after the delay, as (depth, range)
+pub fn catches(firewall: &[(u64, u64)], delay: u64) -> Vec<(u64, u64)> {
+    return firewall.iter()
+        .cloned()
+        .filter(|&(depth, range)| scan_position_from_time(range, delay + depth) == 0)
+        .collect();
+}
+
+fn severity(catches: &[(u64, u64)]) -> u64 {
+    return catches.iter().map(|&(depth, range)| depth * range).sum();
+}
+
+// A picture of the firewall for each picosecond of the trip. Scanners are [S], the packet is in
+// parentheses on the top row and layers without a scanner are "...".
+pub fn render_trip(firewall: &[(u64, u64)], delay: u64) -> String {
+    let layer_count = firewall.iter().map(|&(depth, _)| depth + 1).max().unwrap_or(0);
+    let mut ranges = vec![None; layer_count as usize];
+    for &(depth, range) in firewall.iter() {
+        ranges[depth as usize] = Some(range);
+    }
+    let rows = firewall.iter().map(|&(_, range)| range).max().unwrap_or(1);
+    let header: Vec<String> = (0..layer_count).map(|layer| format!("{:^3}", layer)).collect();
+
+    let mut output = String::new();
+    for packet_layer in 0..layer_count {
+        let time = delay + packet_layer;
+        write!(output, "Picosecond {}, packet in layer {}", time, packet_layer).unwrap();
+        match ranges[packet_layer as usize] {
+            Some(range) if scan_position_from_time(range, time) == 0 =>
+                writeln!(output, ", CAUGHT with severity {}", packet_layer * range).unwrap(),
+            _ => output.push('\n'),
+        }
+        writeln!(output, "{}", header.join(" ").trim_end()).unwrap();
+        for row in 0..rows {
+            let cells: Vec<&str> = ranges.iter().enumerate().map(|(layer, &range)| {
+                let has_packet = row == 0 && layer as u64 == packet_layer;
+                match range {
+                    Some(range) if row < range => {
+                        let has_scanner = scan_position_from_time(range, time) == row;
+                        match (has_packet, has_scanner) {
+                            (true, true) => "(S)",
+                            (true, false) => "( )",
+                            (false, true) => "[S]",
+                            (false, false) => "[ ]",
+                        }
+                    }
+                    None if has_packet => "(.)",
+                    None if row == 0 => "...",
+                    _ => "   ",
+                }
+            }).collect();
+            writeln!(output, "{}", cells.join(" ").trim_end()).unwrap();
+        }
+        output.push('\n');
+    }
+    return output;
+}
+
+// Each layer that catches the packet and its severity, with the total
+pub fn severity_breakdown(firewall: &[(u64, u64)], delay: u64) -> String {
+    let catches = catches(firewall, delay);
+    let mut output = String::new();
+    output.push_str("Layer  Range  Severity\n");
+    for &(depth, range) in catches.iter() {
+        writeln!(output, "{:>5}  {:>5}  {:>8}", depth, range, depth * range).unwrap();
+    }
+    writeln!(output, "Total severity {}, caught {} times", severity(&catches), catches.len()).unwrap();
+    return output;
+}
+
+// How often and how badly the packet is caught for each delay. A delay can be caught with a
+// severity of 0 at layer 0, so the catches are listed too.
+pub fn severity_table(firewall: &[(u64, u64)], delays: RangeInclusive<u64>) -> String {
+    let width = delays.end().to_string().len().max("Delay".len());
+    let mut output = String::new();
+    writeln!(output, "{:>width$}  Catches  Severity", "Delay", width = width).unwrap();
+    for delay in delays {
+        let catches = catches(firewall, delay);
+        writeln!(output, "{:>width$}  {:>7}  {:>8}", delay, catches.len(), severity(&catches), width = width).unwrap();
+    }
+    return output;
+}
+
+#[test]
+fn test_visualize() {
+    let firewall = [(0, 3), (1, 2), (4, 4), (6, 4)];
+    assert_eq!(vec![(0, 3), (6, 4)], catches(&firewall, 0));
+    assert_eq!(super::compute_severity(&firewall), severity(&catches(&firewall, 0)));
+    assert_eq!(Vec::<(u64, u64)>::new(), catches(&firewall, 10));
+
+    let trip = render_trip(&firewall, 0);
+    let expected_start = "\
+Picosecond 0, packet in layer 0, CAUGHT with severity 0
+ 0   1   2   3   4   5   6
+(S) [S] ... ... [S] ... [S]
+[ ] [ ]         [ ]     [ ]
+[ ]             [ ]     [ ]
+                [ ]     [ ]
+
+Picosecond 1, packet in layer 1
+ 0   1   2   3   4   5   6
+[ ] ( ) ... ... [ ] ... [ ]
+[S] [S]         [S]     [S]
+[ ]             [ ]     [ ]
+                [ ]     [ ]
+
+Picosecond 2, packet in layer 2
+";
+    assert!(trip.starts_with(expected_start), "{}", trip);
+    assert_eq!(7, trip.matches("Picosecond").count());
+    assert!(trip.contains("Picosecond 3, packet in layer 3\n 0   1   2   3   4   5   6\n[ ] [ ] ... (.) [ ] ... [ ]\n"));
+    assert!(trip.contains("Picosecond 6, packet in layer 6, CAUGHT with severity 24\n"));
+
+    let breakdown = "Layer  Range  Severity\n    0      3         0\n    6      4        24\nTotal severity 24, caught 2 times\n";
+    assert_eq!(breakdown, severity_breakdown(&firewall, 0));
+
+    let table = severity_table(&firewall, 9..=10);
+    assert_eq!("Delay  Catches  Severity\n    9        1         2\n   10        0         0\n", table);
+    let table = severity_table(&firewall, 1_000_000..=1_000_000);
+    assert_eq!("  Delay  Catches  Severity\n1000000        1         0\n", table);
+}
@@ -0,0 +1,61 @@
+// Faster knot hash for hashing many rows. Instead of swapping one pair of elements at a time with
+// wrapping indices, the ring is stored twice in a row so every reversal is a single contiguous
+// slice, even when it wraps around the end. After reversing, the changed part is copied over the
+// other copy. slice::reverse and copy_from_slice compile down to vectorized loops.
+
+const RING_SIZE: usize = 256;
+const SUFFIX: [u8; 5] = [17, 31, 73, 47, 23];
+
+// Reverses length elements starting at position, in a ring stored twice
+fn reverse_doubled(knot: &mut [u8; RING_SIZE * 2], position: usize, length: usize) {
+    let end = position + length;
+    knot[position..end].reverse();
+    if end <= RING_SIZE {
+        knot.copy_within(position..end, position + RING_SIZE);
+    } else {
+        // The part before the wrap goes to the second copy and the part after to the first
+        knot.copy_within(position..RING_SIZE, position + RING_SIZE);
+        knot.copy_within(RING_SIZE..end, 0);
+    }
+}
+
+pub fn fast_knot_hash(input: &[u8]) -> [u8; 16] {
+    let mut knot = [0; RING_SIZE * 2];
+    for (i, v) in knot.iter_mut().enumerate() {
+        *v = i as u8;
+    }
+    let lengths: Vec<usize> = input.iter().chain(SUFFIX.iter()).map(|&length| length as usize).collect();
+
+    let mut position = 0;
+    let mut skip = 0;
+    for _ in 0..64 {
+        for &length in lengths.iter() {
+            reverse_doubled(&mut knot, position, length);
+            position = (position + length + skip) % RING_SIZE;
+            skip = (skip + 1) % RING_SIZE;
+        }
+    }
+
+    let mut result = [0; 16];
+    for (v, block) in result.iter_mut().zip(knot[..RING_SIZE].chunks(16)) {
+        *v = block.iter().fold(0, |acc, x| acc ^ x);
+    }
+    return result;
+}
+
+#[test]
+fn test_fast_knot_hash() {
+    // Every row of the puzzle's example and some pseudo-random inputs of many lengths
+    let mut inputs: Vec<Vec<u8>> = (0..128).map(|row| format!("flqrgnkx-{}", row).into_bytes()).collect();
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    let lengths: Vec<usize> = (0..40).chain(vec![100, 255, 256, 300]).collect();
+    for &length in lengths.iter() {
+        inputs.push((0..length).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        }).collect());
+    }
+    for input in inputs.iter() {
+        assert_eq!(super::knot_hash(input), fast_knot_hash(input), "{:?}", input);
+    }
+}
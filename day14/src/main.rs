@@ -1,7 +1,21 @@
+#![allow(clippy::needless_return)]
+#![cfg_attr(feature = "bench", feature(test))]
+
+#[cfg(feature = "bench")]
+extern crate test;
+
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::collections::vec_deque;
 
+mod fast_knot;
+
+use fast_knot::fast_knot_hash;
+
+#[cfg(feature = "bench")]
+use test::Bencher;
+
 fn get_input_bytes() -> Vec<u8> {
     let mut f = File::open("input.bin").expect("Could not open file");
     let mut result = Vec::new();
@@ -35,6 +49,7 @@ where
     }
 }
 
+// The straightforward knot hash, kept as the reference for fast_knot_hash
 fn knot_hash(input: &[u8]) -> [u8; 16] {
     let mut knot = [0; 256];
     init_start_knot(&mut knot);
@@ -61,20 +76,27 @@ fn count_bits(mut byte: u8) -> u32 {
         if byte & 0x1 != 0 {
             set_bits += 1;
         }
-        byte = byte >> 1;
+        byte >>= 1;
     }
     return set_bits;
 }
 
 const GRID_SIZE: usize = 128;
 
-fn part1(input: &[u8]) -> u32 {
+// The key with the row number appended, which is hashed to get the row
+fn row_hash_input(input: &[u8], row_index: usize) -> Vec<u8> {
+    let mut hash_bytes = Vec::from(input);
+    let end_str = format!("-{}", row_index);
+    hash_bytes.append(&mut end_str.into_bytes());
+    return hash_bytes;
+}
+
+type KnotHash = fn(&[u8]) -> [u8; 16];
+
+fn part1(input: &[u8], knot_hash: KnotHash) -> u32 {
     let mut set_bits = 0;
     for row_index in 0..GRID_SIZE {
-        let mut hash_bytes = Vec::from(input);
-        let end_str = format!("-{}", row_index);
-        hash_bytes.append(&mut end_str.into_bytes());
-        let row = knot_hash(&hash_bytes);
+        let row = knot_hash(&row_hash_input(input, row_index));
         for &byte in row.iter() {
             set_bits += count_bits(byte);
         }
@@ -114,13 +136,10 @@ fn zero_region(disk: &mut BitGrid, row: usize, col: usize) {
     }
 }
 
-fn part2(input: &[u8]) -> u32 {
+fn part2(input: &[u8], knot_hash: KnotHash) -> u32 {
     let mut disk: BitGrid = [[0; GRID_BYTE_SIZE]; GRID_SIZE];
     for (row_index, row) in disk.iter_mut().enumerate() {
-        let mut hash_bytes = Vec::from(input);
-        let end_str = format!("-{}", row_index);
-        hash_bytes.append(&mut end_str.into_bytes());
-        *row = knot_hash(&hash_bytes);
+        *row = knot_hash(&row_hash_input(input, row_index));
     }
 
     let mut regions = 0;
@@ -135,8 +154,44 @@ fn part2(input: &[u8]) -> u32 {
     return regions;
 }
 
+#[test]
+fn test_example() {
+    for &hash in [knot_hash as KnotHash, fast_knot_hash].iter() {
+        assert_eq!(8108, part1(b"flqrgnkx", hash));
+        assert_eq!(1242, part2(b"flqrgnkx", hash));
+    }
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn reference_knot_hash_bench(b: &mut Bencher) {
+    let input = get_input_bytes();
+    b.iter(|| {
+        for row_index in 0..GRID_SIZE {
+            test::black_box(knot_hash(&row_hash_input(&input, row_index)));
+        }
+    });
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn fast_knot_hash_bench(b: &mut Bencher) {
+    let input = get_input_bytes();
+    b.iter(|| {
+        for row_index in 0..GRID_SIZE {
+            test::black_box(fast_knot_hash(&row_hash_input(&input, row_index)));
+        }
+    });
+}
+
 fn main() {
+    // The fast knot hash is used unless reference is given
+    let hash: KnotHash = match env::args().nth(1).as_deref() {
+        None | Some("fast") => fast_knot_hash,
+        Some("reference") => knot_hash,
+        Some(other) => panic!("Unknown knot hash {}, expected fast or reference", other),
+    };
     let input = get_input_bytes();
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    println!("Part 1: {}", part1(&input, hash));
+    println!("Part 2: {}", part2(&input, hash));
 }